@@ -0,0 +1,79 @@
+//! The per-frame work: advancing the rain and turning a frame into escape
+//! sequences. Output goes to an in-memory buffer, so terminal speed doesn't count.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rusty_matrix::{language_keys, ColorMode, Config, Frame, Rain, Renderer, TerminalRenderer, THEMES};
+use std::hint::black_box;
+
+const WIDTH: u16 = 240;
+const HEIGHT: u16 = 70;
+const LANGUAGE: &str = "Katakana";
+
+/// Rain on a large screen, run long enough that every column is busy.
+fn warm_rain(config: &Config, rng: &mut StdRng) -> Rain {
+    let mut rain = Rain::new(WIDTH, HEIGHT, 1, config);
+    rain.truecolor = true;
+    for _ in 0..200 {
+        rain.update(config, &THEMES[0], LANGUAGE, rng);
+    }
+    rain
+}
+
+fn config() -> Config {
+    let mut config = Config::new(&language_keys());
+    config.density = 1.0;
+    config
+}
+
+fn update(c: &mut Criterion) {
+    let config = config();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut rain = warm_rain(&config, &mut rng);
+    c.bench_function("rain_update", |b| {
+        b.iter(|| rain.update(&config, &THEMES[0], LANGUAGE, &mut rng));
+    });
+}
+
+fn draw(c: &mut Criterion) {
+    let config = config();
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut rain = warm_rain(&config, &mut rng);
+    let mut frame = Frame::new(WIDTH, HEIGHT);
+    c.bench_function("render_to", |b| {
+        b.iter(|| {
+            frame.clear();
+            rain.render_to(&config, &mut frame);
+        });
+    });
+
+    let mut group = c.benchmark_group("terminal_render");
+    for mode in [ColorMode::Ansi16, ColorMode::TrueColor] {
+        let mut renderer = TerminalRenderer::new(Vec::with_capacity(1 << 20), mode);
+        // A full repaint, as after a resize or menu.
+        group.bench_with_input(BenchmarkId::new("full", mode.name()), &mode, |b, _| {
+            b.iter(|| {
+                renderer.writer().clear();
+                renderer.invalidate();
+                renderer.render(&frame).unwrap();
+                black_box(renderer.writer().len());
+            });
+        });
+        // The usual case: only what changed since the previous frame.
+        group.bench_with_input(BenchmarkId::new("diff", mode.name()), &mode, |b, _| {
+            b.iter(|| {
+                rain.update(&config, &THEMES[0], LANGUAGE, &mut rng);
+                frame.clear();
+                rain.render_to(&config, &mut frame);
+                renderer.writer().clear();
+                renderer.render(&frame).unwrap();
+                black_box(renderer.writer().len());
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, update, draw);
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+use crate::baseline::Baseline;
+use crate::config::{Config, SPEED_DURATIONS};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, Stream};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// Scales the input's RMS amplitude, which rarely gets near 1.0, into a 0.0-1.0 level.
+const GAIN: f32 = 4.0;
+
+/// Share of the level kept from one frame to the next, so peaks ease off
+/// instead of flickering out between buffers.
+const DECAY: f32 = 0.9;
+
+/// Listens to the default audio input and pulses the rain with it: louder
+/// sound speeds drops up and starts them more often, by up to
+/// `audio_reactivity` of the way from the picked speed and spawn chance to
+/// their maximums.
+pub struct AudioReactor {
+    /// Held only to keep the input running.
+    _stream: Stream,
+    /// RMS amplitude of the latest buffer, as `f32` bits.
+    amplitude: Arc<AtomicU32>,
+    level: f32,
+}
+
+impl AudioReactor {
+    /// Starts listening, or `None` when there is no input device or it can't
+    /// be opened, in which case the rain just carries on unaffected.
+    pub fn open() -> Option<Self> {
+        let device = cpal::default_host().default_input_device()?;
+        let supported = device.default_input_config().ok()?;
+        let amplitude = Arc::new(AtomicU32::new(0));
+        let stream = match supported.sample_format() {
+            SampleFormat::I8 => listen::<i8>(&device, supported.into(), &amplitude),
+            SampleFormat::I16 => listen::<i16>(&device, supported.into(), &amplitude),
+            SampleFormat::I32 => listen::<i32>(&device, supported.into(), &amplitude),
+            SampleFormat::U8 => listen::<u8>(&device, supported.into(), &amplitude),
+            SampleFormat::U16 => listen::<u16>(&device, supported.into(), &amplitude),
+            SampleFormat::F32 => listen::<f32>(&device, supported.into(), &amplitude),
+            _ => None,
+        }?;
+        stream.play().ok()?;
+        Some(Self { _stream: stream, amplitude, level: 0.0 })
+    }
+
+    /// Sets `config`'s speed and spawn chance for the current level, up from
+    /// `baseline`, dropping any `fps` override. Meant to be called once a frame.
+    pub fn apply(&mut self, baseline: &Baseline, config: &mut Config) {
+        let amplitude = f32::from_bits(self.amplitude.load(Ordering::Relaxed));
+        self.level = (amplitude * GAIN).min(1.0).max(self.level * DECAY);
+        let pulse = self.level * config.audio_reactivity;
+
+        let max_speed = SPEED_DURATIONS.len() as f32;
+        let idle_speed = baseline.speed_level as f32;
+        let speed = idle_speed + (max_speed - idle_speed) * pulse;
+        config.fps = None;
+        config.speed_level = (speed.round() as usize).clamp(1, SPEED_DURATIONS.len());
+        // Quiet passages thin the rain out, loud ones fill every ready column.
+        let reactivity = config.audio_reactivity as f64;
+        let chance = baseline.spawn_chance * (1.0 - reactivity) + reactivity * self.level as f64;
+        config.spawn_chance = chance.clamp(0.0, 1.0);
+    }
+}
+
+/// Opens an input stream that stores each buffer's RMS amplitude in `amplitude`.
+fn listen<T>(device: &cpal::Device, config: cpal::StreamConfig, amplitude: &Arc<AtomicU32>) -> Option<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    // If the device goes away, fall silent and let the last level decay.
+    let silence = Arc::clone(amplitude);
+    let on_error = move |_| silence.store(0.0f32.to_bits(), Ordering::Relaxed);
+    let amplitude = Arc::clone(amplitude);
+    let on_data = move |data: &[T], _: &cpal::InputCallbackInfo| {
+        if data.is_empty() {
+            return;
+        }
+        let sum: f32 = data.iter().map(|&s| f32::from_sample(s).powi(2)).sum();
+        let rms = (sum / data.len() as f32).sqrt();
+        amplitude.store(rms.to_bits(), Ordering::Relaxed);
+    };
+    device.build_input_stream(config, on_data, on_error, None).ok()
+}
@@ -0,0 +1,259 @@
+use std::path::Path;
+
+/// Terminal graphics protocol a backdrop is drawn with.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Protocol {
+    /// kitty's graphics protocol, also spoken by WezTerm and Ghostty. The
+    /// picture sits under the text, so the rain falls over it.
+    Kitty,
+    /// DEC sixel graphics. Sixel pixels can't sit under text, so the rain
+    /// wipes the picture away where it falls until the next full redraw.
+    Sixel,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 2] = [Protocol::Kitty, Protocol::Sixel];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Kitty => "kitty",
+            Protocol::Sixel => "sixel",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Guesses from the environment. Terminals answer a query for their
+    /// graphics support, but the answer arrives on stdin mixed in with the
+    /// keys, so the variables they set are used instead.
+    pub fn detect() -> Option<Self> {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            return Some(Protocol::Kitty);
+        }
+        if term.contains("sixel")
+            || term.starts_with("foot")
+            || term == "mlterm"
+            || matches!(program.as_str(), "mintty" | "iTerm.app")
+        {
+            return Some(Protocol::Sixel);
+        }
+        None
+    }
+}
+
+/// Image id the backdrop is sent to kitty under.
+const KITTY_IMAGE_ID: u32 = 7265;
+
+/// Base64 bytes per kitty escape; the protocol caps chunks at 4096.
+const KITTY_CHUNK: usize = 4096;
+
+/// Removes the backdrop from a kitty terminal, for when the program exits.
+pub const KITTY_DELETE: &str = "\x1b_Ga=d,d=I,i=7265,q=2\x1b\\";
+
+/// Pixels per cell assumed when the terminal doesn't report its size in pixels.
+const FALLBACK_CELL_PIXELS: (u32, u32) = (8, 16);
+
+/// A picture shown once behind the rain, and again after each full redraw
+/// since clearing the screen takes it away.
+pub struct Backdrop {
+    pub protocol: Protocol,
+    /// Written once to hand the terminal the picture.
+    pub setup: String,
+    /// Written with the cursor at the top left to show the picture.
+    pub draw: String,
+}
+
+impl Backdrop {
+    /// Loads the image at `path` to cover `cols` x `rows` cells that are
+    /// `pixels` wide and tall in all, when the terminal says. kitty decodes PNG
+    /// files itself; sixel needs the pixels, so takes binary PPM (`P6`) files,
+    /// which most image tools can write. kitty takes those too.
+    pub fn load(path: &Path, protocol: Protocol, cols: u16, rows: u16, pixels: Option<(u32, u32)>) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let png = bytes.starts_with(b"\x89PNG\r\n\x1a\n");
+        match protocol {
+            Protocol::Kitty if png => Ok(Self::kitty("f=100", &bytes, cols, rows)),
+            Protocol::Kitty => {
+                let image = Image::from_ppm(&bytes)?;
+                let format = format!("f=24,s={},v={}", image.width, image.height);
+                Ok(Self::kitty(&format, &image.pixels, cols, rows))
+            }
+            Protocol::Sixel if png => Err("sixel needs a binary PPM (P6) image, not a PNG".to_string()),
+            Protocol::Sixel => {
+                let (cell_width, cell_height) = FALLBACK_CELL_PIXELS;
+                let (width, height) = pixels.unwrap_or((cols as u32 * cell_width, rows as u32 * cell_height));
+                let image = Image::from_ppm(&bytes)?.fit(width, height);
+                Ok(Self { protocol, setup: String::new(), draw: image.to_sixel() })
+            }
+        }
+    }
+
+    /// Sends `data` in `format` to kitty once, scaled to the whole area and
+    /// placed under the text; each draw then only places it again.
+    fn kitty(format: &str, data: &[u8], cols: u16, rows: u16) -> Self {
+        let encoded = base64(data);
+        let chunks: Vec<&str> = encoded.as_bytes().chunks(KITTY_CHUNK).map(|c| std::str::from_utf8(c).unwrap()).collect();
+        let mut setup = String::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = (i + 1 < chunks.len()) as u8;
+            if i == 0 {
+                setup.push_str(&format!("\x1b_Ga=t,{},i={},q=2,m={};{}\x1b\\", format, KITTY_IMAGE_ID, more, chunk));
+            } else {
+                setup.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+            }
+        }
+        Self { protocol: Protocol::Kitty, setup, draw: kitty_placement(cols, rows) }
+    }
+
+    /// Stretches the picture over a new area. Only kitty can; a sixel picture
+    /// keeps the size it was loaded at.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        if self.protocol == Protocol::Kitty {
+            self.draw = kitty_placement(cols, rows);
+        }
+    }
+}
+
+/// Shows the kitty backdrop stretched over `cols` x `rows` cells. A negative z
+/// puts it under the text, and C=1 leaves the cursor where it was.
+fn kitty_placement(cols: u16, rows: u16) -> String {
+    format!("\x1b_Ga=p,i={},c={},r={},z=-1,C=1,q=2\x1b\\", KITTY_IMAGE_ID, cols, rows)
+}
+
+/// 8-bit RGB pixels, row by row.
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Reads a binary PPM: `P6`, the width, height and largest value
+    /// separated by whitespace and `#` comments, then the pixels.
+    fn from_ppm(bytes: &[u8]) -> Result<Self, String> {
+        let invalid = || "expected a PNG or a binary PPM (P6) image".to_string();
+        let mut pos = 0;
+        let mut fields = Vec::new();
+        while fields.len() < 4 {
+            while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+                if bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid());
+            }
+            fields.push(std::str::from_utf8(&bytes[start..pos]).map_err(|_| invalid())?);
+        }
+        // A single whitespace byte separates the header from the pixels.
+        pos += 1;
+        let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+        let (width, height, max) = (number(fields[1])?, number(fields[2])?, number(fields[3])?);
+        if fields[0] != "P6" || width == 0 || height == 0 || !(1..=255).contains(&max) {
+            return Err(invalid());
+        }
+        let len = width as usize * height as usize * 3;
+        let pixels = bytes.get(pos..pos + len).ok_or_else(|| "the PPM image is cut short".to_string())?;
+        let scale = |v: &u8| (*v as u32 * 255 / max) as u8;
+        Ok(Self { width, height, pixels: pixels.iter().map(scale).collect() })
+    }
+
+    /// Scaled, keeping its shape, to the largest size within `width` x `height`.
+    fn fit(&self, width: u32, height: u32) -> Image {
+        let scale = (width as f32 / self.width as f32).min(height as f32 / self.height as f32);
+        let new_width = ((self.width as f32 * scale) as u32).max(1);
+        let new_height = ((self.height as f32 * scale) as u32).max(1);
+        let mut pixels = Vec::with_capacity(new_width as usize * new_height as usize * 3);
+        for y in 0..new_height {
+            let source_y = (y * self.height / new_height) as usize;
+            for x in 0..new_width {
+                let source_x = (x * self.width / new_width) as usize;
+                let i = (source_y * self.width as usize + source_x) * 3;
+                pixels.extend_from_slice(&self.pixels[i..i + 3]);
+            }
+        }
+        Image { width: new_width, height: new_height, pixels }
+    }
+
+    /// Encodes the image as sixel graphics in the 216 colors of a 6x6x6 cube.
+    fn to_sixel(&self) -> String {
+        const LEVELS: u32 = 6;
+        let color = |i: usize| {
+            let level = |v: u8| (v as u32 * (LEVELS - 1) + 127) / 255;
+            let (r, g, b) = (level(self.pixels[i]), level(self.pixels[i + 1]), level(self.pixels[i + 2]));
+            (r * LEVELS * LEVELS + g * LEVELS + b) as usize
+        };
+        let indexes: Vec<usize> = (0..self.pixels.len()).step_by(3).map(color).collect();
+
+        let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", self.width, self.height);
+        let percent = |level: u32| level * 100 / (LEVELS - 1);
+        for n in 0..(LEVELS * LEVELS * LEVELS) {
+            let (r, g, b) = (n / (LEVELS * LEVELS), n / LEVELS % LEVELS, n % LEVELS);
+            out.push_str(&format!("#{};2;{};{};{}", n, percent(r), percent(g), percent(b)));
+        }
+        let width = self.width as usize;
+        for band in 0..self.height.div_ceil(6) as usize {
+            // One row of sixels per color, each a bit for each of the band's six pixel rows.
+            let mut planes: Vec<Option<Vec<u8>>> = vec![None; 216];
+            for (bit, y) in ((band * 6)..((band * 6 + 6).min(self.height as usize))).enumerate() {
+                for (x, &index) in indexes[y * width..(y + 1) * width].iter().enumerate() {
+                    planes[index].get_or_insert_with(|| vec![0; width])[x] |= 1 << bit;
+                }
+            }
+            for (n, plane) in planes.iter().enumerate() {
+                let Some(plane) = plane else {
+                    continue;
+                };
+                out.push_str(&format!("#{}", n));
+                let mut x = 0;
+                while x < width {
+                    let run = plane[x..].iter().take_while(|&&bits| bits == plane[x]).count();
+                    let char = (63 + plane[x]) as char;
+                    if run > 3 {
+                        out.push_str(&format!("!{}{}", run, char));
+                    } else {
+                        out.extend(std::iter::repeat_n(char, run));
+                    }
+                    x += run;
+                }
+                out.push('$');
+            }
+            out.push('-');
+        }
+        out.push_str("\x1b\\");
+        out
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
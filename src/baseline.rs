@@ -0,0 +1,73 @@
+use crate::config::{frame_interval, Config};
+
+/// The speed, spawn chance and density the user picked, which a speed driver
+/// (the CPU monitor, audio or the wave) swings the rain around. Drivers write
+/// their swing into the `Config` every step, so the user's own changes to
+/// these go through here and the driver follows them from its next step.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Baseline {
+    pub speed_level: usize,
+    pub fps: Option<u32>,
+    pub spawn_chance: f64,
+    pub density: f32,
+}
+
+impl Baseline {
+    pub fn new(config: &Config) -> Self {
+        Self { speed_level: config.speed_level, fps: config.fps, spawn_chance: config.spawn_chance, density: config.density }
+    }
+
+    /// Frames per second at the picked speed.
+    pub fn frames_per_second(&self) -> f64 {
+        1.0 / frame_interval(self.speed_level, self.fps).as_secs_f64()
+    }
+
+    /// Moves the picked speed a level, like [`Config::step_speed`], and shows
+    /// it in `config` until the driver's next step.
+    pub fn step_speed(&mut self, config: &mut Config, faster: bool) {
+        config.speed_level = self.speed_level;
+        config.fps = self.fps;
+        config.step_speed(faster);
+        self.speed_level = config.speed_level;
+        self.fps = config.fps;
+    }
+
+    /// Sets the picked density, in `config` too.
+    pub fn set_density(&mut self, config: &mut Config, density: f32) {
+        self.density = density;
+        config.density = density;
+    }
+
+    /// Puts the picked settings back in `config`, so they are what gets saved.
+    pub fn restore(&self, config: &mut Config) {
+        config.speed_level = self.speed_level;
+        config.fps = self.fps;
+        config.spawn_chance = self.spawn_chance;
+        config.density = self.density;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::language_keys;
+    use crate::wave::{Wave, WaveTarget};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn wave_follows_a_stepped_speed() {
+        let mut config = Config::new(&language_keys());
+        config.wave_target = WaveTarget::Speed;
+        config.wave_amplitude = 0.0;
+        let mut baseline = Baseline::new(&config);
+        let start = Instant::now();
+        let wave = Wave::new(start);
+        wave.apply(&baseline, &mut config, start);
+        assert_eq!(config.fps, Some(19));
+        baseline.step_speed(&mut config, true);
+        wave.apply(&baseline, &mut config, start + Duration::from_secs(1));
+        assert_eq!(config.fps, Some(25));
+        baseline.restore(&mut config);
+        assert_eq!((config.speed_level, config.fps), (6, None));
+    }
+}
@@ -0,0 +1,411 @@
+use crate::config::{normalize_name, Config};
+use once_cell::sync::OnceCell;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+pub struct CharSet {
+    pub chars: Vec<char>,
+    /// Display width of the widest glyph in the set, in terminal cells.
+    pub width: u16,
+    /// Relative odds of each of `chars`; `None` picks them uniformly.
+    weights: Option<WeightedIndex<u32>>,
+}
+
+/// Takes up at least one cell on its own. Combining marks, zero-width
+/// characters and controls don't, and would shift everything after them on
+/// the line.
+fn stands_alone(c: char) -> bool {
+    c.width().is_some_and(|width| width > 0)
+}
+
+impl CharSet {
+    /// A set of `chars`, leaving out any that can't stand alone in a cell.
+    pub fn new(mut chars: Vec<char>) -> Self {
+        chars.retain(|&c| stands_alone(c));
+        let width = chars.iter().filter_map(|c| c.width()).max().unwrap_or(1).max(1) as u16;
+        Self { chars, width, weights: None }
+    }
+
+    /// A set in which `chars[i]` turns up in proportion to `weights[i]`. Fails
+    /// if the two differ in length or no weight is above zero.
+    pub fn weighted(chars: Vec<char>, weights: &[u32]) -> Result<Self, String> {
+        if chars.len() != weights.len() {
+            return Err(format!("{} weights given for {} characters", weights.len(), chars.len()));
+        }
+        let (chars, weights): (Vec<char>, Vec<u32>) =
+            chars.into_iter().zip(weights.iter().copied()).filter(|&(c, _)| stands_alone(c)).unzip();
+        let weights = WeightedIndex::new(&weights).map_err(|_| "every character has a weight of 0".to_string())?;
+        Ok(Self { weights: Some(weights), ..Self::new(chars) })
+    }
+
+    /// A character picked by weight, or uniformly if the set has no weights.
+    /// Returns `FALLBACK_CHAR` if the set is empty.
+    pub fn random_char(&self, rng: &mut impl Rng) -> char {
+        if self.chars.is_empty() {
+            return FALLBACK_CHAR;
+        }
+        match &self.weights {
+            Some(weights) => self.chars[weights.sample(rng)],
+            None => self.chars[rng.gen_range(0..self.chars.len())],
+        }
+    }
+}
+
+/// Drawn in place of a character when the requested set is missing or empty.
+pub const FALLBACK_CHAR: char = '?';
+
+pub static ALL_CHAR_SETS: once_cell::sync::Lazy<HashMap<String, CharSet>> = once_cell::sync::Lazy::new(|| {
+    let mut map = HashMap::new();
+
+    // English Character Set
+    let mut english_chars = Vec::new();
+    english_chars.extend('a'..='z');
+    english_chars.extend('A'..='Z');
+    english_chars.extend('0'..='9');
+    english_chars.extend("!@#$%^&*()_+-=[]{}|;:',.<>/?`~".chars());
+    map.insert("English".to_string(), CharSet::new(english_chars));
+
+    // Traditional Chinese Character Set (Common CJK Unified Ideographs)
+    let mut traditional_chinese_chars = Vec::new();
+    for i in 0x4E00..=0x9FA5 {
+        if let Some(c) = std::char::from_u32(i) {
+            traditional_chinese_chars.push(c);
+        }
+    }
+    map.insert("Traditional Chinese".to_string(), CharSet::new(traditional_chinese_chars));
+
+    // Simplified Chinese Character Set (Broader CJK Unified Ideographs)
+    let mut simplified_chinese_chars = Vec::new();
+    for i in 0x4E00..=0x9FFF {
+        if let Some(c) = std::char::from_u32(i) {
+            simplified_chinese_chars.push(c);
+        }
+    }
+    map.insert("Simplified Chinese".to_string(), CharSet::new(simplified_chinese_chars));
+
+    // Half-width Katakana Character Set (the classic film look)
+    let mut katakana_chars = Vec::new();
+    for i in 0xFF66..=0xFF9D {
+        if let Some(c) = std::char::from_u32(i) {
+            katakana_chars.push(c);
+        }
+    }
+    katakana_chars.extend('0'..='9');
+    map.insert("Katakana".to_string(), CharSet::new(katakana_chars));
+
+    // Greek Character Set (capital and small letters, U+03A2 is unassigned)
+    let mut greek_chars = Vec::new();
+    for i in (0x0391..=0x03C9).filter(|&i| i != 0x03A2) {
+        if let Some(c) = std::char::from_u32(i) {
+            greek_chars.push(c);
+        }
+    }
+    map.insert("Greek".to_string(), CharSet::new(greek_chars));
+
+    // Cyrillic Character Set (basic Russian alphabet)
+    let mut cyrillic_chars = Vec::new();
+    for i in 0x0410..=0x044F {
+        if let Some(c) = std::char::from_u32(i) {
+            cyrillic_chars.push(c);
+        }
+    }
+    map.insert("Cyrillic".to_string(), CharSet::new(cyrillic_chars));
+
+    // Arabic Character Set (letters only; U+0640 TATWEEL is a joining stroke).
+    // Every glyph is placed on its own, so terminals draw the isolated forms.
+    let mut arabic_chars = Vec::new();
+    for i in (0x0620..=0x064A).filter(|&i| i != 0x0640) {
+        if let Some(c) = std::char::from_u32(i) {
+            arabic_chars.push(c);
+        }
+    }
+    map.insert("Arabic".to_string(), CharSet::new(arabic_chars));
+
+    // Hangul Character Set (every precomposed syllable block). These are
+    // full-width, so columns are spaced two cells apart like the CJK sets.
+    let mut hangul_chars = Vec::new();
+    for i in 0xAC00..=0xD7A3 {
+        if let Some(c) = std::char::from_u32(i) {
+            hangul_chars.push(c);
+        }
+    }
+    map.insert("Hangul".to_string(), CharSet::new(hangul_chars));
+
+    // Devanagari Character Set (independent vowels, consonants and digits).
+    // Vowel signs and viramas combine with the character before them, so they
+    // are left out and each cell holds a base character that stands alone.
+    let mut devanagari_chars = Vec::new();
+    for i in (0x0905..=0x0939).chain(0x0966..=0x096F) {
+        if let Some(c) = std::char::from_u32(i) {
+            devanagari_chars.push(c);
+        }
+    }
+    map.insert("Devanagari".to_string(), CharSet::new(devanagari_chars));
+
+    // Circuit Character Set (box drawing and block elements), which falls
+    // like traces on a circuit board. All single-width, so columns stay packed.
+    let mut circuit_chars = Vec::new();
+    for i in 0x2500..=0x259F {
+        if let Some(c) = std::char::from_u32(i) {
+            circuit_chars.push(c);
+        }
+    }
+    map.insert("Circuit".to_string(), CharSet::new(circuit_chars));
+
+    // Binary Character Set
+    map.insert("Binary".to_string(), CharSet::new(vec!['0', '1']));
+
+    // Hexadecimal Character Set
+    let mut hex_chars = Vec::new();
+    hex_chars.extend('0'..='9');
+    hex_chars.extend('a'..='f');
+    map.insert("Hex".to_string(), CharSet::new(hex_chars));
+
+    map
+});
+
+pub const CUSTOM_CHAR_SET: &str = "Custom";
+
+/// User-supplied set, registered once at startup next to the built-in ones.
+static CUSTOM: OnceCell<CharSet> = OnceCell::new();
+
+/// The unique non-whitespace characters of `text`, in order of first appearance.
+pub fn chars_from_text(text: &str) -> Vec<char> {
+    let mut chars = Vec::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+/// A set from the text of a character set file: its characters as found by
+/// [`chars_from_text`], except that a line holding one character and a number,
+/// such as `ｱ 5`, weights that character. It then turns up five times as often
+/// as the characters listed without a weight, which count as 1.
+pub fn char_set_from_text(text: &str) -> Result<CharSet, String> {
+    let mut chars = Vec::new();
+    let mut weights = Vec::new();
+    let mut weighted = false;
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if let (Some(char), Some(weight), None) = (words.next(), words.next(), words.next())
+            && let (Some(c), None) = (char.chars().next(), char.chars().nth(1))
+            && let Ok(weight) = weight.parse::<u32>()
+        {
+            match chars.iter().position(|&existing| existing == c) {
+                Some(i) => weights[i] = weight,
+                None => {
+                    chars.push(c);
+                    weights.push(weight);
+                }
+            }
+            weighted = true;
+            continue;
+        }
+        for c in chars_from_text(line) {
+            if !chars.contains(&c) {
+                chars.push(c);
+                weights.push(1);
+            }
+        }
+    }
+    if weighted { CharSet::weighted(chars, &weights) } else { Ok(CharSet::new(chars)) }
+}
+
+/// Makes `set` selectable as the "Custom" set. Fails if `set` is empty or a
+/// custom set was already registered.
+pub fn register_custom_char_set(set: CharSet) -> Result<(), String> {
+    if set.chars.is_empty() {
+        return Err("the character set is empty".to_string());
+    }
+    CUSTOM.set(set).map_err(|_| "a custom character set is already registered".to_string())
+}
+
+/// Looks up a built-in or custom set by its key.
+pub fn char_set(language_key: &str) -> Option<&'static CharSet> {
+    if language_key == CUSTOM_CHAR_SET {
+        CUSTOM.get()
+    } else {
+        ALL_CHAR_SETS.get(language_key)
+    }
+}
+
+/// Names of all character sets, sorted so menus cycle in the same order on every run.
+pub fn language_keys() -> Vec<String> {
+    let mut keys: Vec<String> = ALL_CHAR_SETS.keys().cloned().collect();
+    if CUSTOM.get().is_some() {
+        keys.push(CUSTOM_CHAR_SET.to_string());
+    }
+    keys.sort();
+    keys
+}
+
+pub fn find_language(name: &str, language_keys: &[String]) -> Option<usize> {
+    let name = normalize_name(name);
+    language_keys.iter().position(|k| normalize_name(k) == name)
+}
+
+/// Picks from the set, by weight if it has weights. Small sets such as Binary
+/// repeat characters often; that is left as is since it reads as authentic
+/// "digital" noise.
+pub fn get_random_char(language_key: &str, rng: &mut impl Rng) -> char {
+    char_set(language_key).map_or(FALLBACK_CHAR, |set| set.random_char(rng))
+}
+
+/// Slot width for `config`'s set at its spacing, or when mixing, the widest
+/// of the mix, so any column can hold double-width glyphs without overlapping
+/// its neighbours.
+pub fn layout_slot_width(config: &Config, language_keys: &[String]) -> u16 {
+    let spaced = |key: &str| config.spacing_for(key).step(slot_width(key));
+    match config.mix.iter().map(|key| spaced(key)).max() {
+        Some(width) => width,
+        None => spaced(&language_keys[config.language_index]),
+    }
+}
+
+/// Width of one column slot: the display width of the set's widest glyph, so
+/// double-width sets never overlap their neighbours.
+pub fn slot_width(language_key: &str) -> u16 {
+    char_set(language_key).map_or(1, |set| set.width)
+}
+
+/// Keys of the sets named in a `--mix` list, with `all` standing for every
+/// set, or the first name that isn't a set.
+pub fn mix_keys(names: &[String], language_keys: &[String]) -> Result<Vec<String>, String> {
+    let mut keys = Vec::new();
+    for name in names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()) {
+        if name.eq_ignore_ascii_case("all") {
+            keys.extend(language_keys.iter().cloned());
+            continue;
+        }
+        let index = find_language(name, language_keys).ok_or_else(|| name.to_string())?;
+        keys.push(language_keys[index].clone());
+    }
+    let mut unique = Vec::new();
+    for key in keys {
+        if !unique.contains(&key) {
+            unique.push(key);
+        }
+    }
+    Ok(unique)
+}
+
+/// Characters paired with a look-alike of their mirror image, each the same
+/// width as its partner. Unicode has no mirrored kana, so of the Katakana set
+/// only the digit 3 changes.
+const MIRROR_PAIRS: [(char, char); 21] = [
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('<', '>'),
+    ('/', '\\'),
+    ('«', '»'),
+    ('‹', '›'),
+    ('≤', '≥'),
+    ('∈', '∋'),
+    ('⊂', '⊃'),
+    ('b', 'd'),
+    ('p', 'q'),
+    ('c', 'ɔ'),
+    ('e', 'ɘ'),
+    ('r', 'ɿ'),
+    ('3', 'Ɛ'),
+    ('E', 'Ǝ'),
+    ('N', 'И'),
+    ('R', 'Я'),
+    ('S', 'Ƨ'),
+    ('L', '⅃'),
+];
+
+/// `char` flipped left to right where Unicode has a look-alike for it,
+/// otherwise `char` itself.
+pub fn mirrored(char: char) -> char {
+    MIRROR_PAIRS
+        .iter()
+        .find_map(|&(a, b)| if char == a { Some(b) } else if char == b { Some(a) } else { None })
+        .unwrap_or(char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn empty_set_falls_back() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(CharSet::new(Vec::new()).random_char(&mut rng), FALLBACK_CHAR);
+        assert_eq!(CharSet::new(Vec::new()).width, 1);
+    }
+
+    #[test]
+    fn missing_set_falls_back() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(get_random_char("No Such Set", &mut rng), FALLBACK_CHAR);
+        assert_eq!(slot_width("No Such Set"), 1);
+    }
+
+    #[test]
+    fn empty_custom_set_is_rejected() {
+        assert!(register_custom_char_set(CharSet::new(chars_from_text(" \n\t "))).is_err());
+        assert_eq!(get_random_char(CUSTOM_CHAR_SET, &mut StdRng::seed_from_u64(0)), FALLBACK_CHAR);
+    }
+
+    #[test]
+    fn text_is_deduplicated_without_whitespace() {
+        assert_eq!(chars_from_text("ab a\nb c"), vec!['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn weighted_lines_skew_the_odds() {
+        let set = char_set_from_text("ab\nb 0\nc 9\n").unwrap();
+        assert_eq!(set.chars, vec!['a', 'b', 'c']);
+        let mut rng = StdRng::seed_from_u64(0);
+        let picks: Vec<char> = (0..1000).map(|_| set.random_char(&mut rng)).collect();
+        assert!(!picks.contains(&'b'));
+        assert!(picks.iter().filter(|&&c| c == 'c').count() > 800);
+        assert!(char_set_from_text("a 0").is_err());
+    }
+
+    #[test]
+    fn hangul_is_spaced_like_cjk() {
+        assert_eq!(slot_width("Hangul"), 2);
+        assert_eq!(slot_width("Devanagari"), 1);
+        let set = char_set("Devanagari").unwrap();
+        assert!(set.chars.iter().all(|c| c.width() == Some(1)));
+    }
+
+    #[test]
+    fn circuit_is_single_width() {
+        assert_eq!(slot_width("Circuit"), 1);
+        let set = char_set("Circuit").unwrap();
+        assert_eq!(set.chars.len(), 0x25A0 - 0x2500);
+    }
+
+    #[test]
+    fn combining_and_zero_width_chars_are_left_out() {
+        // Devanagari consonants with vowel signs and viramas, an acute accent,
+        // a zero-width space and a zero-width joiner.
+        let text = "\u{915}\u{941}\u{916}\u{94d}\u{917}\u{947}\u{902}e\u{301}\u{200b}\u{200d}";
+        let set = char_set_from_text(text).unwrap();
+        assert_eq!(set.chars, vec!['\u{915}', '\u{916}', '\u{917}', 'e']);
+        assert_eq!(set.width, 1);
+        let weighted = char_set_from_text("\u{301} 5\n\u{915} 2\n").unwrap();
+        assert_eq!(weighted.chars, vec!['\u{915}']);
+        assert!(char_set_from_text("\u{301}\u{200b}").unwrap().chars.is_empty());
+    }
+
+    #[test]
+    fn mirror_pairs_keep_their_width() {
+        for (a, b) in MIRROR_PAIRS {
+            assert_eq!(a.width(), b.width(), "{} and {}", a, b);
+            assert_eq!(mirrored(mirrored(a)), a);
+        }
+        assert_eq!(mirrored('ｱ'), 'ｱ');
+    }
+}
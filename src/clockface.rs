@@ -0,0 +1,207 @@
+use crate::charset::{get_random_char, layout_slot_width};
+use crate::config::Config;
+use crate::frame::{Frame, Glyph, BLANK};
+use crate::theme::ColorScheme;
+use rand::Rng;
+
+/// How the digits of the big clock are drawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FaceStyle {
+    /// Seven-segment digits, as on a digital watch.
+    Segments,
+    /// Chunky digits from a 3x5 pixel font.
+    Blocks,
+}
+
+impl FaceStyle {
+    pub const ALL: [FaceStyle; 2] = [FaceStyle::Segments, FaceStyle::Blocks];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FaceStyle::Segments => "segments",
+            FaceStyle::Blocks => "blocks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Largest `big_clock_size`.
+pub const MAX_FACE_SIZE: u16 = 4;
+
+/// Segments lit for each digit: bits a (top), b (upper right), c (lower
+/// right), d (bottom), e (lower left), f (upper left) and g (middle), from
+/// the lowest bit.
+const SEGMENTS: [u8; 10] = [0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f];
+
+/// The 3x5 font for `FaceStyle::Blocks`, a row of three bits per line.
+const BLOCKS: [[u8; 5]; 10] = [
+    [7, 5, 5, 5, 7],
+    [2, 6, 2, 2, 7],
+    [7, 1, 7, 4, 7],
+    [7, 1, 7, 1, 7],
+    [5, 5, 7, 1, 1],
+    [7, 4, 7, 1, 7],
+    [7, 4, 7, 5, 7],
+    [7, 1, 1, 1, 1],
+    [7, 5, 7, 5, 7],
+    [7, 5, 7, 1, 7],
+];
+
+/// Cells between one character of the clock and the next.
+const GAP: u16 = 2;
+
+/// The time in big digits made of rain, held in the middle of the screen
+/// while the rain falls around it. Its characters flicker like trail cells.
+pub struct ClockFace {
+    width: u16,
+    height: u16,
+    lit: Vec<bool>,
+    chars: Vec<char>,
+    text: String,
+}
+
+impl ClockFace {
+    pub fn new() -> Self {
+        Self { width: 0, height: 0, lit: Vec::new(), chars: Vec::new(), text: String::new() }
+    }
+
+    /// Shows `text`, digits and colons, centered on `frame`, and reserves its
+    /// box so the rain drawn afterwards goes around it. A face too big for the
+    /// frame isn't drawn. Its characters come from the current language and
+    /// are spaced like the rain's columns, so wide characters fit.
+    pub fn draw(
+        &mut self,
+        frame: &mut Frame,
+        text: &str,
+        config: &Config,
+        colors: &ColorScheme,
+        language_keys: &[String],
+        rng: &mut impl Rng,
+    ) {
+        if text != self.text {
+            self.layout(text, config.big_clock_style, config.big_clock_size.clamp(1, MAX_FACE_SIZE));
+        }
+        let language_key = &language_keys[config.language_index];
+        let slot_width = layout_slot_width(config, language_keys).max(1);
+        let (width, height) = (self.width * slot_width, self.height);
+        if width + 2 > frame.width() || height + 2 > frame.height() {
+            return;
+        }
+        let left = (frame.width() - width) / 2;
+        let top = (frame.height() - height) / 2;
+
+        for (i, char) in self.chars.iter_mut().enumerate() {
+            if *char == '\0' || rng.gen_bool(config.mutation_rate) {
+                *char = get_random_char(language_key, rng);
+            }
+            if !self.lit[i] {
+                continue;
+            }
+            let (x, y) = ((i as u16 % self.width), (i as u16 / self.width));
+            // The bottom edge of each stroke is lit like a head, the rest like trail.
+            let below = y + 1 < self.height && self.lit[i + self.width as usize];
+            let color = if below { colors.trail } else { colors.head };
+            let glyph = Glyph { char: *char, color, bold: config.attributes && !below, ..BLANK };
+            frame.set(left + x * slot_width, top + y, glyph);
+        }
+        frame.reserve(left - 1, top - 1, width + 2, height + 2);
+    }
+
+    /// Works out which cells of the face are lit for `text`.
+    fn layout(&mut self, text: &str, style: FaceStyle, size: u16) {
+        let shapes: Vec<(u16, u16, Vec<bool>)> = text.chars().map(|c| shape(c, style, size)).collect();
+        self.width = shapes.iter().map(|(w, _, _)| w + GAP).sum::<u16>().saturating_sub(GAP);
+        self.height = shapes.iter().map(|(_, h, _)| *h).max().unwrap_or(0);
+        self.lit = vec![false; self.width as usize * self.height as usize];
+        let mut left = 0;
+        for (w, h, lit) in &shapes {
+            for y in 0..*h {
+                for x in 0..*w {
+                    if lit[(y * w + x) as usize] {
+                        self.lit[(y * self.width + left + x) as usize] = true;
+                    }
+                }
+            }
+            left += w + GAP;
+        }
+        self.chars = vec!['\0'; self.lit.len()];
+        self.text = text.to_string();
+    }
+}
+
+impl Default for ClockFace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Width, height and lit cells of one character of the face. Anything but a
+/// digit or a colon is blank.
+fn shape(c: char, style: FaceStyle, size: u16) -> (u16, u16, Vec<bool>) {
+    // Cells are about twice as tall as they are wide, so strokes across are
+    // one row thick and strokes down two columns.
+    let (width, height) = match style {
+        FaceStyle::Segments => (2 * size + 4, 2 * size + 3),
+        FaceStyle::Blocks => (6 * size, 5 * size),
+    };
+    let mut lit = vec![false; width as usize * height as usize];
+    let mut set = |x: u16, y: u16| lit[(y * width + x) as usize] = true;
+    match (c.to_digit(10), style) {
+        (Some(digit), FaceStyle::Segments) => {
+            let segments = SEGMENTS[digit as usize];
+            let on = |segment: u8| segments & 1 << segment != 0;
+            let middle = size + 1;
+            for y in 0..height {
+                for x in 0..width {
+                    let left = x < 2;
+                    let right = x >= width - 2;
+                    let upper = y <= middle;
+                    let lower = y >= middle;
+                    let lit = (y == 0 && on(0))
+                        || (right && upper && on(1))
+                        || (right && lower && on(2))
+                        || (y == height - 1 && on(3))
+                        || (left && lower && on(4))
+                        || (left && upper && on(5))
+                        || (y == middle && on(6));
+                    if lit {
+                        set(x, y);
+                    }
+                }
+            }
+        }
+        (Some(digit), FaceStyle::Blocks) => {
+            for (row, bits) in BLOCKS[digit as usize].iter().enumerate() {
+                for column in 0..3 {
+                    if bits & 4 >> column == 0 {
+                        continue;
+                    }
+                    for y in row as u16 * size..(row as u16 + 1) * size {
+                        for x in column * 2 * size..(column + 1) * 2 * size {
+                            set(x, y);
+                        }
+                    }
+                }
+            }
+        }
+        (None, _) if c == ':' => {
+            // Two dots between the digits, a third of the way down and up.
+            let dot = size.div_ceil(2);
+            let colon_width = 2 * dot;
+            let mut colon = vec![false; colon_width as usize * height as usize];
+            for center in [height / 3, height - 1 - height / 3] {
+                for y in center.saturating_sub(dot / 2)..(center.saturating_sub(dot / 2) + dot).min(height) {
+                    for x in 0..colon_width {
+                        colon[(y * colon_width + x) as usize] = true;
+                    }
+                }
+            }
+            return (colon_width, height, colon);
+        }
+        _ => {}
+    }
+    (width, height, lit)
+}
@@ -0,0 +1,160 @@
+#[cfg(feature = "terminal")]
+pub use crossterm::style::Color;
+
+/// Without the terminal backend there is no crossterm, so this stands in for
+/// its color type, variant for variant.
+#[cfg(not(feature = "terminal"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Color {
+    Reset,
+    Black,
+    DarkGrey,
+    Red,
+    DarkRed,
+    Green,
+    DarkGreen,
+    Yellow,
+    DarkYellow,
+    Blue,
+    DarkBlue,
+    Magenta,
+    DarkMagenta,
+    Cyan,
+    DarkCyan,
+    White,
+    Grey,
+    Rgb { r: u8, g: u8, b: u8 },
+    AnsiValue(u8),
+}
+
+/// Approximate RGB value of any color, using the xterm defaults for the named
+/// and indexed palettes. `Reset` has no fixed value.
+pub fn to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let rgb = match color {
+        Color::Reset => return None,
+        Color::Black => (0, 0, 0),
+        Color::DarkGrey => (128, 128, 128),
+        Color::Red => (255, 0, 0),
+        Color::DarkRed => (128, 0, 0),
+        Color::Green => (0, 255, 0),
+        Color::DarkGreen => (0, 128, 0),
+        Color::Yellow => (255, 255, 0),
+        Color::DarkYellow => (128, 128, 0),
+        Color::Blue => (0, 0, 255),
+        Color::DarkBlue => (0, 0, 128),
+        Color::Magenta => (255, 0, 255),
+        Color::DarkMagenta => (128, 0, 128),
+        Color::Cyan => (0, 255, 255),
+        Color::DarkCyan => (0, 128, 128),
+        Color::White => (255, 255, 255),
+        Color::Grey => (192, 192, 192),
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(n) => ansi256_to_rgb(n),
+    };
+    Some(rgb)
+}
+
+/// RGB color for `hue` in degrees (any value, wrapped to 0-360) and
+/// `saturation` and `value` from 0.0 to 1.0.
+pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    Color::Rgb { r: channel(r), g: channel(g), b: channel(b) }
+}
+
+/// `color` turned `degrees` around the color wheel, keeping its saturation and
+/// value. Colors without an RGB value or a hue, and whole turns, give the
+/// color back as it is.
+pub fn rotate_hue(color: Color, degrees: f32) -> Color {
+    let Some((r, g, b)) = to_rgb(color) else {
+        return color;
+    };
+    if degrees.rem_euclid(360.0) == 0.0 {
+        return color;
+    }
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let c = max - r.min(g).min(b);
+    if c == 0.0 {
+        return color;
+    }
+    let hue = if max == r {
+        60.0 * ((g - b) / c)
+    } else if max == g {
+        60.0 * ((b - r) / c + 2.0)
+    } else {
+        60.0 * ((r - g) / c + 4.0)
+    };
+    from_hsv(hue + degrees, c / max, max)
+}
+
+fn ansi256_to_rgb(n: u8) -> (u8, u8, u8) {
+    const BASIC: [Color; 16] = [
+        Color::Black, Color::DarkRed, Color::DarkGreen, Color::DarkYellow,
+        Color::DarkBlue, Color::DarkMagenta, Color::DarkCyan, Color::Grey,
+        Color::DarkGrey, Color::Red, Color::Green, Color::Yellow,
+        Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    ];
+    match n {
+        0..=15 => to_rgb(BASIC[n as usize]).unwrap(),
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = n - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// Blends `from` towards `to` by `t` (0.0 to 1.0) in RGB. Colors without an RGB
+/// value snap to whichever end is closer.
+pub fn lerp(from: Color, to: Color, t: f32) -> Color {
+    let (Some(a), Some(b)) = (to_rgb(from), to_rgb(to)) else {
+        return if t < 0.5 { from } else { to };
+    };
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color::Rgb { r: mix(a.0, b.0), g: mix(a.1, b.1), b: mix(a.2, b.2) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_turns_keep_the_color() {
+        for color in [Color::Green, Color::Rgb { r: 12, g: 200, b: 90 }, Color::AnsiValue(99)] {
+            assert_eq!(rotate_hue(color, 0.0), color);
+            assert_eq!(rotate_hue(color, 360.0), color);
+            assert_eq!(rotate_hue(color, -720.0), color);
+        }
+    }
+
+    #[test]
+    fn red_turns_to_green_and_blue() {
+        let red = Color::Rgb { r: 255, g: 0, b: 0 };
+        assert_eq!(rotate_hue(red, 120.0), Color::Rgb { r: 0, g: 255, b: 0 });
+        assert_eq!(rotate_hue(Color::Red, 240.0), Color::Rgb { r: 0, g: 0, b: 255 });
+    }
+
+    #[test]
+    fn colors_without_a_hue_pass_through() {
+        for color in [Color::Reset, Color::Black, Color::White, Color::Grey, Color::DarkGrey] {
+            assert_eq!(rotate_hue(color, 120.0), color);
+        }
+    }
+}
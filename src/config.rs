@@ -0,0 +1,452 @@
+use crate::clockface::FaceStyle;
+use crate::color::Color;
+use crate::overlay::Corner;
+use crate::rain::{Depth, Direction};
+use crate::wave::WaveTarget;
+use crate::render::ColorMode;
+use crate::screenshot::ScreenshotFormat;
+use crate::theme::{find_theme, THEMES};
+use rand::Rng;
+use std::time::Duration;
+
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    pub theme_index: usize,
+    pub speed_level: usize, // 1-10
+    /// Target frame rate; overrides `speed_level` when set.
+    pub fps: Option<u32>,
+    /// Most frames drawn per second, whatever the rate the rain moves at; when
+    /// it is lower, several steps of the rain go by between draws.
+    pub max_fps: Option<u32>,
+    pub language_index: usize,
+    /// Character sets, by key, that columns pick from at random so several
+    /// scripts rain at once; empty uses `language_index` everywhere.
+    pub mix: Vec<String>,
+    pub save_on_exit: bool,
+    /// Dim the rain to black over a few frames on quit instead of clearing at once.
+    pub fade_out: bool,
+    /// Cross-fade from one theme's colors to the next when switching live.
+    /// Needs truecolor.
+    pub theme_fade: bool,
+    pub seed: Option<u64>,
+    /// Number of distinct shades in a gradient trail; `None` blends every cell.
+    pub gradient_steps: Option<usize>,
+    /// Shortest drop, in cells.
+    pub min_len: u16,
+    /// Longest drop, in cells; `None` means half the column height.
+    pub max_len: Option<u16>,
+    /// Fraction of column slots that carry drops, from 0.0 (none) to 1.0 (all).
+    pub density: f32,
+    /// How many cells apart column slots are.
+    pub spacing: Spacing,
+    /// Spacing for particular character sets, by key, over `spacing`.
+    pub set_spacing: Vec<(String, Spacing)>,
+    /// Per-frame chance that a trail cell swaps its glyph for a new one.
+    pub mutation_rate: f64,
+    /// Frames a column waits before starting another drop, picked at random
+    /// from `idle_min..=idle_max`.
+    pub idle_min: u16,
+    pub idle_max: u16,
+    /// Most drops falling in one column at the same time. Each one is no
+    /// faster than the drop ahead of it, so they never run into each other.
+    pub max_drops: usize,
+    /// Most columns with drops falling at the same time, across the whole
+    /// screen; `None` lets every column rain.
+    pub max_active: Option<usize>,
+    /// Cells above the entry edge a new drop starts, picked at random from
+    /// `start_offset_min..=start_offset_max`, so heads don't all enter at once.
+    pub start_offset_min: u16,
+    pub start_offset_max: u16,
+    /// Per-frame chance that a column ready for a new drop starts one; lower
+    /// values leave columns empty for longer, for sparser rain.
+    pub spawn_chance: f64,
+    /// Frames each column waits between moves, picked per drop from
+    /// `speed_jitter_min..=speed_jitter_max`; 1 moves every frame. This varies
+    /// columns against each other, while `speed_level` sets the frame rate.
+    pub speed_jitter_min: u16,
+    pub speed_jitter_max: u16,
+    /// Let drops move by fractions of a cell, showing the cell they're moving
+    /// into brighten as they near it. Needs truecolor.
+    pub smooth_motion: bool,
+    /// Frames a new head takes to brighten from black to the head color;
+    /// 0 shows it at full brightness at once. Needs truecolor.
+    pub head_fade_in: u16,
+    /// Chance that a new head character flashes bright for a frame.
+    pub glint_rate: f64,
+    /// Chance that a new drop is drawn bold from head to tail.
+    pub bold_drop_chance: f64,
+    /// Cells from the head on drawn in the head color before the trail takes
+    /// over; 1 lights only the head. No longer than `min_len`.
+    pub head_length: u16,
+    /// Theme names mapped to their own `head_length`.
+    pub theme_head_length: Vec<(String, u16)>,
+    /// How much of a drop, counting the head, is drawn in the trail color
+    /// before the fade color takes over. Only affects stepped shading.
+    pub bright_trail: BrightTrail,
+    /// Draw heads bold and faded tails dim, on terminals that render them well.
+    pub attributes: bool,
+    /// Which way the rain travels.
+    pub direction: Direction,
+    /// Brightens or dims drops by their speed. Needs truecolor.
+    pub depth: Depth,
+    /// Slots per second the wind steadily blows the rain sideways; negative
+    /// blows the other way.
+    pub wind_bias: f32,
+    /// Slots the wind sways the rain either side of its course, over a cycle of
+    /// `wind_period` seconds.
+    pub wind: f32,
+    pub wind_period: f32,
+    /// Text spelled out by the rain every `message_interval` seconds.
+    pub message: Option<String>,
+    pub message_interval: u64,
+    /// Show the time, optionally with the date, in `clock_corner`.
+    pub clock: bool,
+    pub clock_date: bool,
+    pub clock_corner: Corner,
+    /// Spell the time in large `big_clock_style` digits made of rain in the
+    /// middle of the screen, `big_clock_size` (1-4) times the smallest.
+    pub big_clock: bool,
+    pub big_clock_style: FaceStyle,
+    pub big_clock_size: u16,
+    pub big_clock_seconds: bool,
+    /// Show frames per second and frame time, for debugging performance.
+    pub show_fps: bool,
+    /// Name the theme and language in the terminal's title while running.
+    pub set_title: bool,
+    /// Move overlay text by a cell every few minutes, so a screensaver left
+    /// running doesn't burn it into an OLED screen.
+    pub shift_overlays: bool,
+    /// Speed the rain up and fill it in as CPU usage rises, up to
+    /// `cpu_speed_level` and `cpu_density` at full load.
+    pub cpu_monitor: bool,
+    pub cpu_speed_level: usize,
+    pub cpu_density: f32,
+    /// Show the CPU usage `cpu_monitor` is reacting to.
+    pub show_cpu: bool,
+    /// Pulse the rain's speed and spawn chance with the default audio input,
+    /// by up to `audio_reactivity` (0.0-1.0) of the way to their maximums.
+    /// Needs the `audio` feature.
+    pub audio: bool,
+    pub audio_reactivity: f32,
+    /// Swell and ebb `wave_target` on a sine cycle `wave_period` seconds long,
+    /// by up to `wave_amplitude` (0.0-1.0) of its starting value either way.
+    pub wave: bool,
+    pub wave_target: WaveTarget,
+    pub wave_period: f32,
+    pub wave_amplitude: f32,
+    /// Degrees the rainbow theme's hue moves each frame.
+    pub rainbow_speed: f32,
+    /// Turn the whole palette of any theme around the color wheel,
+    /// `hue_shift_speed` degrees a second. Needs truecolor.
+    pub hue_shift: bool,
+    pub hue_shift_speed: f32,
+    pub screenshot_format: ScreenshotFormat,
+    /// Colors to draw with; `None` detects what the terminal supports.
+    pub color_mode: Option<ColorMode>,
+    /// Fill behind the rain; `None` keeps the terminal's own background.
+    pub background: Option<Color>,
+    /// Draw heads with foreground and background swapped.
+    pub inverse_heads: bool,
+    /// Swap brackets, some Latin letters and the digit 3 for mirrored
+    /// look-alikes. Unicode has no mirrored kana, so Katakana hardly changes.
+    pub mirror: bool,
+    /// Overlay bands of interference now and then.
+    pub glitch: bool,
+    /// Per-frame chance of a new glitch band.
+    pub glitch_rate: f64,
+    /// Fraction of the characters in a band that get scrambled.
+    pub glitch_intensity: f64,
+    /// Strike a random column with lightning now and then.
+    pub lightning: bool,
+    /// Per-frame chance of a lightning strike.
+    pub lightning_rate: f64,
+    /// How far a bolt's color goes from the theme's head color toward white.
+    pub lightning_brightness: f32,
+    /// Let a trail cell that runs out linger, with `ghost_chance`, as a very
+    /// dark ghost of its last character for `ghost_frames` frames.
+    pub ghosts: bool,
+    pub ghost_chance: f64,
+    pub ghost_frames: u8,
+}
+
+/// How far one column slot is from the next.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Spacing {
+    /// As wide as the set's widest glyph.
+    Glyph,
+    /// One cell.
+    Single,
+    /// Two cells, leaving a gap beside single-width glyphs.
+    Double,
+}
+
+impl Spacing {
+    pub const ALL: [Spacing; 3] = [Spacing::Glyph, Spacing::Single, Spacing::Double];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Spacing::Glyph => "glyph",
+            Spacing::Single => "1",
+            Spacing::Double => "2",
+        }
+    }
+
+    /// Takes `single` and `double` as well as the numbers.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match normalize_name(name).as_str() {
+            "single" => Some(Spacing::Single),
+            "double" => Some(Spacing::Double),
+            name => Self::ALL.into_iter().find(|s| s.name() == name),
+        }
+    }
+
+    /// Slot width for a set whose glyphs are up to `glyph_width` cells wide.
+    /// Never narrower than the glyphs, so wide sets such as Hangul keep their
+    /// width whatever the spacing.
+    pub fn step(self, glyph_width: u16) -> u16 {
+        match self {
+            Spacing::Glyph | Spacing::Single => glyph_width.max(1),
+            Spacing::Double => glyph_width.max(2),
+        }
+    }
+}
+
+/// Length of the bright front part of a drop.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BrightTrail {
+    /// A fixed number of cells.
+    Cells(u16),
+    /// The head and a fraction, from 0.0 to 1.0, of the cells behind it.
+    Fraction(f32),
+}
+
+impl BrightTrail {
+    /// Bright cells in a drop `len` cells long, never more than the drop itself.
+    /// A nonzero fraction keeps the head and one cell behind it bright on
+    /// short drops.
+    pub fn cells(self, len: i16) -> i16 {
+        let cells = match self {
+            BrightTrail::Cells(cells) => cells.min(i16::MAX as u16) as i16,
+            BrightTrail::Fraction(fraction) if fraction > 0.0 => 1 + ((fraction * (len - 1) as f32).round() as i16).max(1),
+            BrightTrail::Fraction(_) => 0,
+        };
+        cells.clamp(0, len.max(0))
+    }
+}
+
+pub const DEFAULT_LANGUAGE: &str = "Katakana";
+
+/// Default share of each drop drawn in the bright trail color.
+pub const DEFAULT_BRIGHT_FRACTION: f32 = 0.25;
+
+pub const SPEED_DURATIONS: [u64; 10] = [100, 88, 76, 64, 52, 40, 33, 28, 24, 20];
+
+/// Upper limit for `max_drops`.
+pub const MAX_DROPS: usize = 8;
+
+/// Upper limit for `head_fade_in`.
+pub const MAX_HEAD_FADE_IN: u16 = 10;
+
+/// Upper limit for `ghost_frames`.
+pub const MAX_GHOST_FRAMES: u8 = 60;
+
+/// Upper limit for `wind` and for either direction of `wind_bias`.
+pub const MAX_WIND: f32 = 100.0;
+
+/// Highest frame rate `fps` is held to.
+pub const MAX_FPS: u32 = 120;
+
+/// Glint and mutation rates for `--classic`: a flash now and then, and trails
+/// that keep flickering into new characters.
+const CLASSIC_GLINT_RATE: f64 = 0.03;
+const CLASSIC_MUTATION_RATE: f64 = 0.05;
+
+impl Config {
+    pub fn new(language_keys: &[String]) -> Self {
+        let language_index = language_keys.iter().position(|k| k == DEFAULT_LANGUAGE).unwrap_or(0);
+        Self {
+            theme_index: 0,
+            speed_level: 5,
+            fps: None,
+            max_fps: None,
+            language_index,
+            mix: Vec::new(),
+            save_on_exit: true,
+            fade_out: true,
+            theme_fade: true,
+            seed: None,
+            gradient_steps: None,
+            min_len: 5,
+            max_len: None,
+            density: 0.5,
+            spacing: Spacing::Glyph,
+            set_spacing: Vec::new(),
+            mutation_rate: 0.02,
+            idle_min: 0,
+            idle_max: 30,
+            max_drops: 1,
+            max_active: None,
+            start_offset_min: 0,
+            start_offset_max: 20,
+            spawn_chance: 1.0,
+            speed_jitter_min: 1,
+            speed_jitter_max: 4,
+            smooth_motion: false,
+            head_fade_in: 0,
+            head_length: 1,
+            bold_drop_chance: 0.1,
+            theme_head_length: Vec::new(),
+            glint_rate: 0.02,
+            bright_trail: BrightTrail::Fraction(DEFAULT_BRIGHT_FRACTION),
+            attributes: true,
+            direction: Direction::Down,
+            depth: Depth::Off,
+            wind_bias: 0.0,
+            wind: 0.0,
+            wind_period: 10.0,
+            message: None,
+            message_interval: 10,
+            clock: false,
+            clock_date: false,
+            clock_corner: Corner::TopRight,
+            big_clock: false,
+            big_clock_style: FaceStyle::Segments,
+            big_clock_size: 2,
+            big_clock_seconds: false,
+            show_fps: false,
+            set_title: false,
+            shift_overlays: false,
+            cpu_monitor: false,
+            cpu_speed_level: SPEED_DURATIONS.len(),
+            cpu_density: 1.0,
+            show_cpu: false,
+            audio: false,
+            audio_reactivity: 0.5,
+            wave: false,
+            wave_target: WaveTarget::Spawn,
+            wave_period: 20.0,
+            wave_amplitude: 0.5,
+            rainbow_speed: 1.0,
+            hue_shift: false,
+            hue_shift_speed: 6.0,
+            screenshot_format: ScreenshotFormat::Ansi,
+            color_mode: None,
+            background: None,
+            inverse_heads: false,
+            mirror: false,
+            glitch: false,
+            glitch_rate: 0.02,
+            glitch_intensity: 0.5,
+            lightning: false,
+            lightning_rate: 0.005,
+            lightning_brightness: 1.0,
+            ghosts: false,
+            ghost_chance: 0.3,
+            ghost_frames: 6,
+        }
+    }
+
+    /// Time between frames: `1000 / fps` ms when a frame rate is set, otherwise
+    /// the duration for `speed_level`.
+    pub fn frame_interval(&self) -> Duration {
+        frame_interval(self.speed_level, self.fps)
+    }
+
+    /// Shortest time between draws, when `max_fps` caps them.
+    pub fn draw_interval(&self) -> Option<Duration> {
+        self.max_fps.map(|fps| Duration::from_millis(1000 / fps.clamp(1, MAX_FPS) as u64))
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        1.0 / self.frame_interval().as_secs_f64()
+    }
+
+    /// Moves one speed level faster or slower. This drops any `fps` override,
+    /// so the change always shows.
+    pub fn step_speed(&mut self, faster: bool) {
+        self.fps = None;
+        self.speed_level = if faster {
+            (self.speed_level + 1).min(SPEED_DURATIONS.len())
+        } else {
+            (self.speed_level - 1).max(1)
+        };
+    }
+
+    /// Cells per frame for a new drop: one over a frames-per-move value from
+    /// the speed jitter range. That value is a whole number of frames unless
+    /// `smooth_motion` allows anything in between.
+    pub fn random_velocity(&self, rng: &mut impl Rng) -> f32 {
+        let min = self.speed_jitter_min.max(1);
+        let max = self.speed_jitter_max.max(min);
+        let frames = if self.smooth_motion {
+            rng.gen_range(min as f32..=max as f32)
+        } else {
+            rng.gen_range(min..=max) as f32
+        };
+        1.0 / frames
+    }
+
+    /// Spacing for the set with key `language_key`.
+    pub fn spacing_for(&self, language_key: &str) -> Spacing {
+        self.set_spacing.iter().find(|(key, _)| key == language_key).map_or(self.spacing, |&(_, spacing)| spacing)
+    }
+
+    /// Head length for the theme named `theme`.
+    pub fn head_length_for(&self, theme: &str) -> u16 {
+        self.theme_head_length.iter().find(|(name, _)| name == theme).map_or(self.head_length, |&(_, len)| len)
+    }
+
+    /// Moves `min_len` a cell longer, up to `max`, or shorter. It stays at
+    /// least as long as every head length, which are checked against it.
+    pub fn step_min_len(&mut self, longer: bool, max: u16) {
+        if longer {
+            self.min_len = (self.min_len + 1).min(max);
+        } else {
+            let longest_head = self.theme_head_length.iter().map(|&(_, len)| len).fold(self.head_length, u16::max);
+            self.min_len = (self.min_len - 1).max(longest_head).max(1);
+        }
+    }
+
+    /// Range drop lengths are drawn from for a column `height` cells tall, kept
+    /// within the column and never empty.
+    pub fn len_range(&self, height: u16) -> (i16, i16) {
+        let max = self.max_len.unwrap_or(height / 2).min(height).max(1);
+        let min = self.min_len.min(max).max(1);
+        (min as i16, max as i16)
+    }
+
+    /// Sets everything `--classic` covers, over whatever the config file said.
+    pub fn apply_classic(&mut self, language_keys: &[String]) {
+        let defaults = Config::new(language_keys);
+        self.theme_index = find_theme("Classic Green", &THEMES).unwrap_or(0);
+        self.language_index = defaults.language_index;
+        self.mix.clear();
+        self.mirror = true;
+        self.direction = Direction::Down;
+        self.spacing = Spacing::Glyph;
+        self.set_spacing.clear();
+        self.gradient_steps = None;
+        self.bright_trail = defaults.bright_trail;
+        self.attributes = true;
+        self.inverse_heads = false;
+        self.glint_rate = CLASSIC_GLINT_RATE;
+        self.mutation_rate = CLASSIC_MUTATION_RATE;
+        self.head_length = 1;
+        self.theme_head_length.clear();
+        self.bold_drop_chance = 0.0;
+    }
+}
+
+/// Time between frames at `speed_level`, or at `fps` when that is set.
+pub fn frame_interval(speed_level: usize, fps: Option<u32>) -> Duration {
+    match fps {
+        Some(fps) => Duration::from_millis(1000 / fps.clamp(1, MAX_FPS) as u64),
+        None => Duration::from_millis(SPEED_DURATIONS[speed_level - 1]),
+    }
+}
+
+/// Lowercased with spaces, dashes and underscores removed, so `cyberpunk`,
+/// `classic-green` and `Classic Green` all name the same theme.
+pub fn normalize_name(name: &str) -> String {
+    name.chars().filter(|c| !matches!(c, ' ' | '-' | '_')).flat_map(char::to_lowercase).collect()
+}
@@ -0,0 +1,1004 @@
+use crate::charset::{find_language, mix_keys};
+use crate::clockface::{FaceStyle, MAX_FACE_SIZE};
+use crate::config::{
+    BrightTrail, Config, Spacing, MAX_DROPS, MAX_FPS, MAX_GHOST_FRAMES, MAX_HEAD_FADE_IN, MAX_WIND, SPEED_DURATIONS,
+};
+use crate::keys::{key_name, parse_key, Action, KeyBindings};
+use crate::overlay::Corner;
+use crate::rain::{Depth, Direction};
+use crate::render::ColorMode;
+use crate::screenshot::ScreenshotFormat;
+use crate::theme::{find_theme, format_hex_color, parse_hex_color, ColorScheme, CUSTOM_THEME};
+use crate::wave::WaveTarget;
+use serde::Deserialize;
+use std::io::Read;
+use std::path::Path;
+
+/// The config file, `~/.config/rusty_matrix/config.toml` unless `--config`
+/// says otherwise. Every table and key in it is optional.
+#[derive(Deserialize, Default)]
+pub struct ConfigFile {
+    #[serde(default)]
+    matrix: MatrixSection,
+    #[serde(default)]
+    theme: ThemeSection,
+    /// `[keys]`: action names mapped to a key name or a list of them.
+    #[serde(default)]
+    keys: toml::Table,
+    /// `[spacing]`: character set names mapped to their own spacing.
+    #[serde(default)]
+    spacing: toml::Table,
+    /// `[head_length]`: theme names mapped to their own head length.
+    #[serde(default)]
+    head_length: toml::Table,
+}
+
+#[derive(Deserialize, Default)]
+struct MatrixSection {
+    theme: Option<String>,
+    theme_index: Option<usize>,
+    speed_level: Option<usize>,
+    fps: Option<u32>,
+    max_fps: Option<u32>,
+    language: Option<String>,
+    mix: Option<Vec<String>>,
+    save_on_exit: Option<bool>,
+    fade_out: Option<bool>,
+    theme_fade: Option<bool>,
+    seed: Option<u64>,
+    gradient_steps: Option<usize>,
+    min_len: Option<u16>,
+    max_len: Option<u16>,
+    density: Option<f32>,
+    /// A name such as `glyph`, or `1` or `2` written as a number or a string.
+    spacing: Option<toml::Value>,
+    mutation_rate: Option<f64>,
+    idle_min: Option<u16>,
+    idle_max: Option<u16>,
+    start_offset_min: Option<u16>,
+    start_offset_max: Option<u16>,
+    speed_jitter_min: Option<u16>,
+    speed_jitter_max: Option<u16>,
+    smooth_motion: Option<bool>,
+    max_drops: Option<usize>,
+    max_active: Option<usize>,
+    spawn_chance: Option<f64>,
+    head_fade_in: Option<u16>,
+    glint_rate: Option<f64>,
+    bold_drop_chance: Option<f64>,
+    head_length: Option<u16>,
+    bright_trail: Option<u16>,
+    bright_trail_fraction: Option<f32>,
+    attributes: Option<bool>,
+    direction: Option<String>,
+    depth: Option<String>,
+    message: Option<String>,
+    message_interval: Option<u64>,
+    clock: Option<bool>,
+    clock_date: Option<bool>,
+    clock_corner: Option<String>,
+    big_clock: Option<bool>,
+    big_clock_style: Option<String>,
+    big_clock_size: Option<u16>,
+    big_clock_seconds: Option<bool>,
+    rainbow_speed: Option<f32>,
+    hue_shift: Option<bool>,
+    hue_shift_speed: Option<f32>,
+    wind: Option<f32>,
+    wind_bias: Option<f32>,
+    wind_period: Option<f32>,
+    color_mode: Option<String>,
+    background: Option<String>,
+    inverse_heads: Option<bool>,
+    mirror: Option<bool>,
+    set_title: Option<bool>,
+    shift_overlays: Option<bool>,
+    screenshot_format: Option<String>,
+    glitch: Option<bool>,
+    glitch_rate: Option<f64>,
+    glitch_intensity: Option<f64>,
+    lightning: Option<bool>,
+    lightning_rate: Option<f64>,
+    lightning_brightness: Option<f32>,
+    ghosts: Option<bool>,
+    ghost_chance: Option<f64>,
+    ghost_frames: Option<u8>,
+    cpu_monitor: Option<bool>,
+    cpu_speed_level: Option<usize>,
+    cpu_density: Option<f32>,
+    show_cpu: Option<bool>,
+    audio: Option<bool>,
+    audio_reactivity: Option<f32>,
+    wave: Option<bool>,
+    wave_target: Option<String>,
+    wave_period: Option<f32>,
+    wave_amplitude: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+struct ThemeSection {
+    custom: Option<CustomTheme>,
+}
+
+/// `[theme.custom]`: three `#RRGGBB` strings.
+#[derive(Deserialize)]
+struct CustomTheme {
+    head: String,
+    trail: String,
+    fade: String,
+}
+
+impl CustomTheme {
+    fn to_scheme(&self) -> Result<ColorScheme, String> {
+        Ok(ColorScheme {
+            name: CUSTOM_THEME,
+            head: parse_hex_color(&self.head)?,
+            trail: parse_hex_color(&self.trail)?,
+            fade: parse_hex_color(&self.fade)?,
+            background: None,
+        })
+    }
+}
+
+/// `--config` path that reads the config from stdin instead of a file.
+const STDIN_PATH: &str = "-";
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATH
+}
+
+impl ConfigFile {
+    /// Reads a config from its TOML text.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+
+    /// Reads the config at `path`. A missing file is silently treated as empty;
+    /// an unreadable or malformed one is an error, for the caller to report and
+    /// go on without. A path of `-` reads the config from stdin, which is then
+    /// used up; keys are still read from the terminal itself.
+    pub fn read(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let read = if is_stdin(path) {
+            let mut contents = String::new();
+            std::io::stdin().read_to_string(&mut contents).map(|_| contents)
+        } else {
+            std::fs::read_to_string(path)
+        };
+        match read {
+            Ok(contents) => {
+                Self::parse(&contents).map_err(|e| format!("ignoring malformed config {}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// The `[theme.custom]` table as a theme, if there is one.
+    pub fn custom_theme(&self) -> Option<Result<ColorScheme, String>> {
+        self.theme.custom.as_ref().map(CustomTheme::to_scheme)
+    }
+}
+
+/// Applies the `[keys]` table over the default bindings. Unknown actions and
+/// keys are skipped with a warning, and so is each key a binding takes from
+/// another action.
+pub fn load_key_bindings(file: &ConfigFile) -> (KeyBindings, Vec<String>) {
+    let mut bindings = KeyBindings::default();
+    let mut warnings = Vec::new();
+    for (name, value) in &file.keys {
+        let Some(action) = Action::from_name(name) else {
+            warnings.push(format!("ignoring unknown key action '{}'", name));
+            continue;
+        };
+        let names: Vec<&str> = match value {
+            toml::Value::String(key) => vec![key.as_str()],
+            toml::Value::Array(keys) if keys.iter().all(toml::Value::is_str) => {
+                keys.iter().filter_map(toml::Value::as_str).collect()
+            }
+            _ => {
+                warnings.push(format!("ignoring keys.{}: expected a key name or a list of them", name));
+                continue;
+            }
+        };
+        let mut keys = Vec::new();
+        for key_text in names {
+            match parse_key(key_text) {
+                Some(key) if !keys.contains(&key) => keys.push(key),
+                Some(_) => {}
+                None => warnings.push(format!("ignoring unknown key '{}' for {}", key_text, action.name())),
+            }
+        }
+        if keys.is_empty() {
+            continue;
+        }
+        for (key, other) in bindings.bind(action, keys) {
+            warnings.push(format!(
+                "key '{}' is bound to both {} and {}; it now does {}",
+                key_name(key),
+                other.name(),
+                action.name(),
+                action.name()
+            ));
+        }
+    }
+    (bindings, warnings)
+}
+
+/// Builds the startup `Config` from the config file. Invalid settings are kept
+/// at their defaults, with a warning for each.
+pub fn load_config(file: &ConfigFile, themes: &[ColorScheme], language_keys: &[String]) -> (Config, Vec<String>) {
+    let mut config = Config::new(language_keys);
+    let mut warnings = Vec::new();
+    let matrix = &file.matrix;
+
+    if let Some(name) = &matrix.theme {
+        match find_theme(name, themes) {
+            Some(index) => config.theme_index = index,
+            None => warnings.push(format!("unknown theme '{}', using {}", name, themes[0].name)),
+        }
+    } else if let Some(index) = matrix.theme_index {
+        if index < themes.len() {
+            config.theme_index = index;
+        } else {
+            warnings.push(format!("theme_index {} is out of range, using {}", index, themes[0].name));
+        }
+    }
+
+    if let Some(level) = matrix.speed_level {
+        if (1..=SPEED_DURATIONS.len()).contains(&level) {
+            config.speed_level = level;
+        } else {
+            warnings.push(format!("speed_level must be between 1 and {}, got {}", SPEED_DURATIONS.len(), level));
+        }
+    }
+    if let Some(fps) = matrix.fps {
+        if (1..=MAX_FPS).contains(&fps) {
+            config.fps = Some(fps);
+        } else {
+            warnings.push(format!("fps must be between 1 and {}, got {}", MAX_FPS, fps));
+        }
+    }
+    if let Some(fps) = matrix.max_fps {
+        if (1..=MAX_FPS).contains(&fps) {
+            config.max_fps = Some(fps);
+        } else {
+            warnings.push(format!("max_fps must be between 1 and {}, got {}", MAX_FPS, fps));
+        }
+    }
+
+    if let Some(name) = &matrix.language {
+        match find_language(name, language_keys) {
+            Some(index) => config.language_index = index,
+            None => warnings.push(format!("unknown language '{}', using {}", name, language_keys[config.language_index])),
+        }
+    }
+    if let Some(names) = &matrix.mix {
+        match mix_keys(names, language_keys) {
+            Ok(keys) => config.mix = keys,
+            Err(name) => warnings.push(format!("ignoring mix: unknown language '{}'", name)),
+        }
+    }
+
+    if let Some(save_on_exit) = matrix.save_on_exit {
+        config.save_on_exit = save_on_exit;
+    }
+    config.seed = matrix.seed;
+
+    if let Some(steps) = matrix.gradient_steps {
+        if steps >= 2 {
+            config.gradient_steps = Some(steps);
+        } else {
+            warnings.push(format!("gradient_steps must be at least 2, got {}", steps));
+        }
+    }
+
+    let min_len = matrix.min_len.unwrap_or(config.min_len);
+    match validate_len_range(min_len, matrix.max_len.or(config.max_len)) {
+        Ok(()) => {
+            config.min_len = min_len;
+            config.max_len = matrix.max_len.or(config.max_len);
+        }
+        Err(e) => warnings.push(format!("ignoring min_len/max_len: {}", e)),
+    }
+
+    if let Some(density) = matrix.density {
+        if (0.0..=1.0).contains(&density) {
+            config.density = density;
+        } else {
+            warnings.push(format!("density must be between 0.0 and 1.0, got {}", density));
+        }
+    }
+    if let Some(value) = &matrix.spacing {
+        match spacing_value(value) {
+            Some(spacing) => config.spacing = spacing,
+            None => warnings.push(format!("unknown spacing {}, expected one of {}", value, spacing_names())),
+        }
+    }
+    for (name, value) in &file.spacing {
+        let Some(index) = find_language(name, language_keys) else {
+            warnings.push(format!("ignoring spacing for unknown language '{}'", name));
+            continue;
+        };
+        match spacing_value(value) {
+            Some(spacing) => config.set_spacing.push((language_keys[index].clone(), spacing)),
+            None => warnings.push(format!("ignoring spacing.{}: expected one of {}", name, spacing_names())),
+        }
+    }
+
+    if let Some(rate) = matrix.mutation_rate {
+        if (0.0..=1.0).contains(&rate) {
+            config.mutation_rate = rate;
+        } else {
+            warnings.push(format!("mutation_rate must be between 0.0 and 1.0, got {}", rate));
+        }
+    }
+
+    let idle_min = matrix.idle_min.unwrap_or(config.idle_min);
+    let idle_max = matrix.idle_max.unwrap_or(config.idle_max);
+    if idle_min <= idle_max {
+        config.idle_min = idle_min;
+        config.idle_max = idle_max;
+    } else {
+        warnings.push(format!("ignoring idle_min/idle_max: idle_min {} is greater than idle_max {}", idle_min, idle_max));
+    }
+    let start_offset_min = matrix.start_offset_min.unwrap_or(config.start_offset_min);
+    let start_offset_max = matrix.start_offset_max.unwrap_or(config.start_offset_max);
+    if start_offset_min <= start_offset_max {
+        config.start_offset_min = start_offset_min;
+        config.start_offset_max = start_offset_max;
+    } else {
+        warnings.push(format!(
+            "ignoring start_offset_min/start_offset_max: start_offset_min {} is greater than start_offset_max {}",
+            start_offset_min, start_offset_max
+        ));
+    }
+
+    if let Some(max_drops) = matrix.max_drops {
+        if (1..=MAX_DROPS).contains(&max_drops) {
+            config.max_drops = max_drops;
+        } else {
+            warnings.push(format!("max_drops must be between 1 and {}, got {}", MAX_DROPS, max_drops));
+        }
+    }
+    match matrix.max_active {
+        Some(0) => warnings.push("max_active must be at least 1".to_string()),
+        max_active => config.max_active = max_active,
+    }
+
+    if let Some(chance) = matrix.spawn_chance {
+        if (0.0..=1.0).contains(&chance) {
+            config.spawn_chance = chance;
+        } else {
+            warnings.push(format!("spawn_chance must be between 0.0 and 1.0, got {}", chance));
+        }
+    }
+
+    if let Some(frames) = matrix.head_fade_in {
+        if frames <= MAX_HEAD_FADE_IN {
+            config.head_fade_in = frames;
+        } else {
+            warnings.push(format!("head_fade_in must be at most {} frames, got {}", MAX_HEAD_FADE_IN, frames));
+        }
+    }
+
+    let jitter_min = matrix.speed_jitter_min.unwrap_or(config.speed_jitter_min);
+    let jitter_max = matrix.speed_jitter_max.unwrap_or(config.speed_jitter_max);
+    match validate_speed_jitter(jitter_min, jitter_max) {
+        Ok(()) => {
+            config.speed_jitter_min = jitter_min;
+            config.speed_jitter_max = jitter_max;
+        }
+        Err(e) => warnings.push(format!("ignoring speed_jitter_min/speed_jitter_max: {}", e)),
+    }
+
+    if let Some(smooth) = matrix.smooth_motion {
+        config.smooth_motion = smooth;
+    }
+
+    if let Some(rate) = matrix.glint_rate {
+        if (0.0..=1.0).contains(&rate) {
+            config.glint_rate = rate;
+        } else {
+            warnings.push(format!("glint_rate must be between 0.0 and 1.0, got {}", rate));
+        }
+    }
+    if let Some(chance) = matrix.bold_drop_chance {
+        if (0.0..=1.0).contains(&chance) {
+            config.bold_drop_chance = chance;
+        } else {
+            warnings.push(format!("bold_drop_chance must be between 0.0 and 1.0, got {}", chance));
+        }
+    }
+    if let Some(len) = matrix.head_length {
+        match validate_head_length(len, config.min_len) {
+            Ok(()) => config.head_length = len,
+            Err(e) => warnings.push(format!("ignoring head_length: {}", e)),
+        }
+    }
+    for (name, value) in &file.head_length {
+        let Some(index) = find_theme(name, themes) else {
+            warnings.push(format!("ignoring head_length for unknown theme '{}'", name));
+            continue;
+        };
+        let len = value.as_integer().and_then(|len| u16::try_from(len).ok());
+        match len.map(|len| (len, validate_head_length(len, config.min_len))) {
+            Some((len, Ok(()))) => config.theme_head_length.push((themes[index].name.to_string(), len)),
+            Some((_, Err(e))) => warnings.push(format!("ignoring head_length.{}: {}", name, e)),
+            None => warnings.push(format!("ignoring head_length.{}: expected a number of cells", name)),
+        }
+    }
+
+    match (matrix.bright_trail, matrix.bright_trail_fraction) {
+        (Some(_), Some(_)) => warnings.push("ignoring bright_trail/bright_trail_fraction: set only one of them".to_string()),
+        (Some(cells), None) => config.bright_trail = BrightTrail::Cells(cells),
+        (None, Some(fraction)) if (0.0..=1.0).contains(&fraction) => {
+            config.bright_trail = BrightTrail::Fraction(fraction);
+        }
+        (None, Some(fraction)) => {
+            warnings.push(format!("bright_trail_fraction must be between 0.0 and 1.0, got {}", fraction))
+        }
+        (None, None) => {}
+    }
+
+    if let Some(attributes) = matrix.attributes {
+        config.attributes = attributes;
+    }
+    if let Some(fade_out) = matrix.fade_out {
+        config.fade_out = fade_out;
+    }
+    if let Some(theme_fade) = matrix.theme_fade {
+        config.theme_fade = theme_fade;
+    }
+
+    if let Some(name) = &matrix.direction {
+        match Direction::from_name(name) {
+            Some(direction) => config.direction = direction,
+            None => warnings.push(format!("unknown direction '{}', expected one of {}", name, direction_names())),
+        }
+    }
+    if let Some(name) = &matrix.depth {
+        match Depth::from_name(name) {
+            Some(depth) => config.depth = depth,
+            None => warnings.push(format!("unknown depth '{}', expected one of {}", name, depth_names())),
+        }
+    }
+
+    if matrix.message.is_some() {
+        config.message = matrix.message.clone();
+    }
+    if let Some(interval) = matrix.message_interval {
+        if interval > 0 {
+            config.message_interval = interval;
+        } else {
+            warnings.push("message_interval must be at least 1 second".to_string());
+        }
+    }
+
+    if let Some(clock) = matrix.clock {
+        config.clock = clock;
+    }
+    if let Some(clock_date) = matrix.clock_date {
+        config.clock_date = clock_date;
+    }
+    if let Some(big_clock) = matrix.big_clock {
+        config.big_clock = big_clock;
+    }
+    if let Some(name) = &matrix.big_clock_style {
+        match FaceStyle::from_name(name) {
+            Some(style) => config.big_clock_style = style,
+            None => warnings.push(format!("unknown big_clock_style '{}', expected one of {}", name, face_style_names())),
+        }
+    }
+    if let Some(size) = matrix.big_clock_size {
+        if (1..=MAX_FACE_SIZE).contains(&size) {
+            config.big_clock_size = size;
+        } else {
+            warnings.push(format!("big_clock_size must be between 1 and {}, got {}", MAX_FACE_SIZE, size));
+        }
+    }
+    if let Some(seconds) = matrix.big_clock_seconds {
+        config.big_clock_seconds = seconds;
+    }
+    if let Some(speed) = matrix.rainbow_speed {
+        if (0.0..=360.0).contains(&speed) {
+            config.rainbow_speed = speed;
+        } else {
+            warnings.push(format!("rainbow_speed must be between 0 and 360 degrees, got {}", speed));
+        }
+    }
+    if let Some(hue_shift) = matrix.hue_shift {
+        config.hue_shift = hue_shift;
+    }
+    if let Some(speed) = matrix.hue_shift_speed {
+        if (0.0..=360.0).contains(&speed) {
+            config.hue_shift_speed = speed;
+        } else {
+            warnings.push(format!("hue_shift_speed must be between 0 and 360 degrees, got {}", speed));
+        }
+    }
+    if let Some(wind) = matrix.wind {
+        if (0.0..=MAX_WIND).contains(&wind) {
+            config.wind = wind;
+        } else {
+            warnings.push(format!("wind must be between 0 and {} slots, got {}", MAX_WIND, wind));
+        }
+    }
+    if let Some(bias) = matrix.wind_bias {
+        if (-MAX_WIND..=MAX_WIND).contains(&bias) {
+            config.wind_bias = bias;
+        } else {
+            warnings.push(format!("wind_bias must be between -{} and {} slots per second, got {}", MAX_WIND, MAX_WIND, bias));
+        }
+    }
+    if let Some(period) = matrix.wind_period {
+        if period.is_finite() && period > 0.0 {
+            config.wind_period = period;
+        } else {
+            warnings.push(format!("wind_period must be a positive number of seconds, got {}", period));
+        }
+    }
+    if let Some(name) = &matrix.color_mode {
+        match ColorMode::from_name(name) {
+            Some(mode) => config.color_mode = Some(mode),
+            None => warnings.push(format!("unknown color_mode '{}', expected one of {}", name, color_mode_names())),
+        }
+    }
+    if let Some(hex) = &matrix.background {
+        match parse_hex_color(hex) {
+            Ok(color) => config.background = Some(color),
+            Err(e) => warnings.push(format!("ignoring background: {}", e)),
+        }
+    }
+    if let Some(inverse_heads) = matrix.inverse_heads {
+        config.inverse_heads = inverse_heads;
+    }
+    if let Some(mirror) = matrix.mirror {
+        config.mirror = mirror;
+    }
+    if let Some(set_title) = matrix.set_title {
+        config.set_title = set_title;
+    }
+    if let Some(shift_overlays) = matrix.shift_overlays {
+        config.shift_overlays = shift_overlays;
+    }
+    if let Some(glitch) = matrix.glitch {
+        config.glitch = glitch;
+    }
+    if let Some(rate) = matrix.glitch_rate {
+        if (0.0..=1.0).contains(&rate) {
+            config.glitch_rate = rate;
+        } else {
+            warnings.push(format!("glitch_rate must be between 0.0 and 1.0, got {}", rate));
+        }
+    }
+    if let Some(intensity) = matrix.glitch_intensity {
+        if (0.0..=1.0).contains(&intensity) {
+            config.glitch_intensity = intensity;
+        } else {
+            warnings.push(format!("glitch_intensity must be between 0.0 and 1.0, got {}", intensity));
+        }
+    }
+    if let Some(lightning) = matrix.lightning {
+        config.lightning = lightning;
+    }
+    if let Some(rate) = matrix.lightning_rate {
+        if (0.0..=1.0).contains(&rate) {
+            config.lightning_rate = rate;
+        } else {
+            warnings.push(format!("lightning_rate must be between 0.0 and 1.0, got {}", rate));
+        }
+    }
+    if let Some(brightness) = matrix.lightning_brightness {
+        if (0.0..=1.0).contains(&brightness) {
+            config.lightning_brightness = brightness;
+        } else {
+            warnings.push(format!("lightning_brightness must be between 0.0 and 1.0, got {}", brightness));
+        }
+    }
+    if let Some(ghosts) = matrix.ghosts {
+        config.ghosts = ghosts;
+    }
+    if let Some(chance) = matrix.ghost_chance {
+        if (0.0..=1.0).contains(&chance) {
+            config.ghost_chance = chance;
+        } else {
+            warnings.push(format!("ghost_chance must be between 0.0 and 1.0, got {}", chance));
+        }
+    }
+    if let Some(frames) = matrix.ghost_frames {
+        if (1..=MAX_GHOST_FRAMES).contains(&frames) {
+            config.ghost_frames = frames;
+        } else {
+            warnings.push(format!("ghost_frames must be between 1 and {}, got {}", MAX_GHOST_FRAMES, frames));
+        }
+    }
+    if let Some(cpu_monitor) = matrix.cpu_monitor {
+        config.cpu_monitor = cpu_monitor;
+    }
+    if let Some(level) = matrix.cpu_speed_level {
+        if (1..=SPEED_DURATIONS.len()).contains(&level) {
+            config.cpu_speed_level = level;
+        } else {
+            warnings.push(format!("cpu_speed_level must be between 1 and {}, got {}", SPEED_DURATIONS.len(), level));
+        }
+    }
+    if let Some(density) = matrix.cpu_density {
+        if (0.0..=1.0).contains(&density) {
+            config.cpu_density = density;
+        } else {
+            warnings.push(format!("cpu_density must be between 0.0 and 1.0, got {}", density));
+        }
+    }
+    if let Some(show_cpu) = matrix.show_cpu {
+        config.show_cpu = show_cpu;
+    }
+    if let Some(audio) = matrix.audio {
+        config.audio = audio;
+    }
+    if let Some(reactivity) = matrix.audio_reactivity {
+        if (0.0..=1.0).contains(&reactivity) {
+            config.audio_reactivity = reactivity;
+        } else {
+            warnings.push(format!("audio_reactivity must be between 0.0 and 1.0, got {}", reactivity));
+        }
+    }
+    if let Some(wave) = matrix.wave {
+        config.wave = wave;
+    }
+    if let Some(name) = &matrix.wave_target {
+        match WaveTarget::from_name(name) {
+            Some(target) => config.wave_target = target,
+            None => warnings.push(format!("unknown wave_target '{}', expected one of {}", name, wave_target_names())),
+        }
+    }
+    if let Some(period) = matrix.wave_period {
+        if period.is_finite() && period > 0.0 {
+            config.wave_period = period;
+        } else {
+            warnings.push(format!("wave_period must be a positive number of seconds, got {}", period));
+        }
+    }
+    if let Some(amplitude) = matrix.wave_amplitude {
+        if (0.0..=1.0).contains(&amplitude) {
+            config.wave_amplitude = amplitude;
+        } else {
+            warnings.push(format!("wave_amplitude must be between 0.0 and 1.0, got {}", amplitude));
+        }
+    }
+    if let Some(name) = &matrix.screenshot_format {
+        match ScreenshotFormat::from_name(name) {
+            Some(format) => config.screenshot_format = format,
+            None => warnings.push(format!("unknown screenshot_format '{}', expected one of {}", name, screenshot_format_names())),
+        }
+    }
+    if let Some(name) = &matrix.clock_corner {
+        match Corner::from_name(name) {
+            Some(corner) => config.clock_corner = corner,
+            None => warnings.push(format!("unknown clock_corner '{}', expected one of {}", name, corner_names())),
+        }
+    }
+
+    (config, warnings)
+}
+
+/// Writes the current settings back to the `[matrix]` table of the config file,
+/// by name rather than index. Other keys and tables already in the file are kept.
+/// A config read from stdin has nowhere to go back to, so isn't saved.
+pub fn save_config(path: Option<&Path>, config: &Config, themes: &[ColorScheme], language_keys: &[String]) -> std::io::Result<()> {
+    let Some(path) = path.filter(|path| !is_stdin(path)) else {
+        return Ok(());
+    };
+    let mut document = match std::fs::read_to_string(path) {
+        Ok(contents) => contents.parse::<toml::Table>().map_err(std::io::Error::other)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(e),
+    };
+
+    let theme = &themes[config.theme_index];
+    let matrix = table_mut(&mut document, "matrix")?;
+    matrix.remove("theme_index");
+    matrix.insert("theme".into(), theme.name.into());
+    matrix.insert("speed_level".into(), (config.speed_level as i64).into());
+    match config.fps {
+        Some(fps) => matrix.insert("fps".into(), (fps as i64).into()),
+        None => matrix.remove("fps"),
+    };
+    matrix.insert("language".into(), language_keys[config.language_index].clone().into());
+    matrix.insert("min_len".into(), (config.min_len as i64).into());
+    match config.max_len {
+        Some(max_len) => matrix.insert("max_len".into(), (max_len as i64).into()),
+        None => matrix.remove("max_len"),
+    };
+    matrix.insert("density".into(), (config.density as f64).into());
+    match config.max_active {
+        Some(max_active) => matrix.insert("max_active".into(), (max_active as i64).into()),
+        None => matrix.remove("max_active"),
+    };
+    matrix.insert("direction".into(), config.direction.name().into());
+
+    // Keep a custom theme loadable without the flags it may have come from.
+    if theme.name == CUSTOM_THEME {
+        let custom = table_mut(table_mut(&mut document, "theme")?, "custom")?;
+        for (key, color) in [("head", theme.head), ("trail", theme.trail), ("fade", theme.fade)] {
+            if let Some(hex) = format_hex_color(color) {
+                custom.insert(key.into(), hex.into());
+            }
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(&document).map_err(std::io::Error::other)?;
+    std::fs::write(path, contents)
+}
+
+pub fn validate_len_range(min_len: u16, max_len: Option<u16>) -> Result<(), String> {
+    if min_len < 1 || max_len == Some(0) {
+        return Err("lengths must be at least 1".to_string());
+    }
+    match max_len {
+        Some(max_len) if min_len > max_len => Err(format!("min_len {} is greater than max_len {}", min_len, max_len)),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_head_length(len: u16, min_len: u16) -> Result<(), String> {
+    if len < 1 {
+        return Err("the head must be at least 1 cell".to_string());
+    }
+    if len > min_len {
+        return Err(format!("head length {} is longer than min_len {}", len, min_len));
+    }
+    Ok(())
+}
+
+pub fn validate_speed_jitter(min: u16, max: u16) -> Result<(), String> {
+    if min < 1 {
+        return Err("frames between moves must be at least 1".to_string());
+    }
+    if max > i16::MAX as u16 {
+        return Err(format!("frames between moves must be at most {}", i16::MAX));
+    }
+    if min > max {
+        return Err(format!("speed_jitter_min {} is greater than speed_jitter_max {}", min, max));
+    }
+    Ok(())
+}
+
+/// The resolved settings as a JSON object for `--print-config`, keyed like the
+/// `[matrix]` table, with the theme and language by name and the frame rate
+/// the speed works out to. Options left unset are `null`.
+pub fn config_json(config: &Config, themes: &[ColorScheme], language_keys: &[String]) -> serde_json::Value {
+    use serde_json::Value;
+    // Through the shortest decimal, or 0.3 would print as 0.30000001192092896.
+    let float = |x: f32| x.to_string().parse::<f64>().map_or(Value::Null, Value::from);
+    let (bright_trail, bright_trail_fraction) = match config.bright_trail {
+        BrightTrail::Cells(cells) => (Value::from(cells), Value::Null),
+        BrightTrail::Fraction(fraction) => (Value::Null, float(fraction)),
+    };
+    let set_spacing: serde_json::Map<String, Value> =
+        config.set_spacing.iter().map(|(key, spacing)| (key.clone(), spacing.name().into())).collect();
+    let theme_head_length: serde_json::Map<String, Value> =
+        config.theme_head_length.iter().map(|(name, len)| (name.clone(), (*len).into())).collect();
+    let fields: Vec<(&str, Value)> = vec![
+        ("theme", themes[config.theme_index].name.into()),
+        ("speed_level", config.speed_level.into()),
+        ("fps", config.fps.into()),
+        ("max_fps", config.max_fps.into()),
+        ("frames_per_second", config.frames_per_second().into()),
+        ("language", language_keys[config.language_index].clone().into()),
+        ("mix", config.mix.clone().into()),
+        ("save_on_exit", config.save_on_exit.into()),
+        ("fade_out", config.fade_out.into()),
+        ("theme_fade", config.theme_fade.into()),
+        ("seed", config.seed.into()),
+        ("gradient_steps", config.gradient_steps.into()),
+        ("min_len", config.min_len.into()),
+        ("max_len", config.max_len.into()),
+        ("density", float(config.density)),
+        ("spacing", config.spacing.name().into()),
+        ("set_spacing", set_spacing.into()),
+        ("mutation_rate", config.mutation_rate.into()),
+        ("idle_min", config.idle_min.into()),
+        ("idle_max", config.idle_max.into()),
+        ("start_offset_min", config.start_offset_min.into()),
+        ("start_offset_max", config.start_offset_max.into()),
+        ("speed_jitter_min", config.speed_jitter_min.into()),
+        ("speed_jitter_max", config.speed_jitter_max.into()),
+        ("smooth_motion", config.smooth_motion.into()),
+        ("max_drops", config.max_drops.into()),
+        ("max_active", config.max_active.into()),
+        ("spawn_chance", config.spawn_chance.into()),
+        ("head_fade_in", config.head_fade_in.into()),
+        ("glint_rate", config.glint_rate.into()),
+        ("bold_drop_chance", config.bold_drop_chance.into()),
+        ("head_length", config.head_length.into()),
+        ("theme_head_length", theme_head_length.into()),
+        ("bright_trail", bright_trail),
+        ("bright_trail_fraction", bright_trail_fraction),
+        ("attributes", config.attributes.into()),
+        ("direction", config.direction.name().into()),
+        ("depth", config.depth.name().into()),
+        ("message", config.message.clone().into()),
+        ("message_interval", config.message_interval.into()),
+        ("clock", config.clock.into()),
+        ("clock_date", config.clock_date.into()),
+        ("clock_corner", config.clock_corner.name().into()),
+        ("big_clock", config.big_clock.into()),
+        ("big_clock_style", config.big_clock_style.name().into()),
+        ("big_clock_size", config.big_clock_size.into()),
+        ("big_clock_seconds", config.big_clock_seconds.into()),
+        ("show_fps", config.show_fps.into()),
+        ("set_title", config.set_title.into()),
+        ("shift_overlays", config.shift_overlays.into()),
+        ("rainbow_speed", float(config.rainbow_speed)),
+        ("hue_shift", config.hue_shift.into()),
+        ("hue_shift_speed", float(config.hue_shift_speed)),
+        ("wind", float(config.wind)),
+        ("wind_bias", float(config.wind_bias)),
+        ("wind_period", float(config.wind_period)),
+        ("color_mode", config.color_mode.map(ColorMode::name).into()),
+        ("background", config.background.and_then(format_hex_color).into()),
+        ("inverse_heads", config.inverse_heads.into()),
+        ("mirror", config.mirror.into()),
+        ("screenshot_format", config.screenshot_format.name().into()),
+        ("glitch", config.glitch.into()),
+        ("glitch_rate", config.glitch_rate.into()),
+        ("glitch_intensity", config.glitch_intensity.into()),
+        ("lightning", config.lightning.into()),
+        ("lightning_rate", config.lightning_rate.into()),
+        ("lightning_brightness", float(config.lightning_brightness)),
+        ("ghosts", config.ghosts.into()),
+        ("ghost_chance", config.ghost_chance.into()),
+        ("ghost_frames", config.ghost_frames.into()),
+        ("cpu_monitor", config.cpu_monitor.into()),
+        ("cpu_speed_level", config.cpu_speed_level.into()),
+        ("cpu_density", float(config.cpu_density)),
+        ("show_cpu", config.show_cpu.into()),
+        ("audio", config.audio.into()),
+        ("audio_reactivity", float(config.audio_reactivity)),
+        ("wave", config.wave.into()),
+        ("wave_target", config.wave_target.name().into()),
+        ("wave_period", float(config.wave_period)),
+        ("wave_amplitude", float(config.wave_amplitude)),
+    ];
+    Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+fn table_mut<'a>(table: &'a mut toml::Table, key: &str) -> std::io::Result<&'a mut toml::Table> {
+    table
+        .entry(key)
+        .or_insert_with(|| toml::Table::new().into())
+        .as_table_mut()
+        .ok_or_else(|| std::io::Error::other(format!("[{}] is not a table", key)))
+}
+
+pub fn direction_names() -> String {
+    Direction::ALL.map(Direction::name).join(", ")
+}
+
+pub fn depth_names() -> String {
+    Depth::ALL.map(Depth::name).join(", ")
+}
+
+pub fn spacing_names() -> String {
+    Spacing::ALL.map(Spacing::name).join(", ")
+}
+
+/// A spacing from the config file, where `1` and `2` may be numbers.
+fn spacing_value(value: &toml::Value) -> Option<Spacing> {
+    match value {
+        toml::Value::String(name) => Spacing::from_name(name),
+        toml::Value::Integer(cells) => Spacing::from_name(&cells.to_string()),
+        _ => None,
+    }
+}
+
+pub fn wave_target_names() -> String {
+    WaveTarget::ALL.map(WaveTarget::name).join(", ")
+}
+
+pub fn corner_names() -> String {
+    Corner::ALL.map(Corner::name).join(", ")
+}
+
+pub fn face_style_names() -> String {
+    FaceStyle::ALL.map(FaceStyle::name).join(", ")
+}
+
+pub fn color_mode_names() -> String {
+    ColorMode::ALL.map(ColorMode::name).join(", ")
+}
+
+pub fn screenshot_format_names() -> String {
+    ScreenshotFormat::ALL.map(ScreenshotFormat::name).join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::language_keys;
+    use crate::theme::THEMES;
+    use crossterm::event::KeyCode;
+
+    fn load(text: &str) -> (Config, Vec<String>) {
+        load_config(&ConfigFile::parse(text).unwrap(), &THEMES, &language_keys())
+    }
+
+    #[test]
+    fn settings_come_from_the_file() {
+        let (config, warnings) = load(
+            "[matrix]\ntheme = \"cyberpunk\"\ndirection = \"up\"\ndensity = 0.3\nspacing = 2\n\n[head_length]\n\"Ocean Blue\" = 3\n",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(THEMES[config.theme_index].name, "Cyberpunk");
+        assert_eq!(config.direction, Direction::Up);
+        assert_eq!(config.density, 0.3);
+        assert_eq!(config.spacing, Spacing::Double);
+        assert_eq!(config.head_length_for("Ocean Blue"), 3);
+    }
+
+    #[test]
+    fn bad_values_keep_their_defaults_with_a_warning() {
+        let defaults = Config::new(&language_keys());
+        let (config, warnings) =
+            load("[matrix]\nspeed_level = 42\ntheme = \"Nope\"\nhead_length = 9\nmessage = \"hi\"\nwave_period = inf\n");
+        assert_eq!(config.speed_level, defaults.speed_level);
+        assert_eq!(config.theme_index, 0);
+        assert_eq!(config.head_length, defaults.head_length);
+        assert_eq!(config.message.as_deref(), Some("hi"));
+        assert_eq!(
+            warnings,
+            [
+                "unknown theme 'Nope', using Classic Green",
+                "speed_level must be between 1 and 10, got 42",
+                "ignoring head_length: head length 9 is longer than min_len 5",
+                "wave_period must be a positive number of seconds, got inf",
+            ]
+        );
+    }
+
+    #[test]
+    fn menu_edits_save_a_config_that_reloads_cleanly() {
+        let path = std::env::temp_dir().join(format!("rusty_matrix-test-{}.toml", std::process::id()));
+        let text = "[matrix]\nmin_len = 3\nhead_length = 2\n\n[head_length]\nCyberpunk = 3\n";
+        std::fs::write(&path, text).unwrap();
+        let (mut config, _) = load(text);
+        config.step_min_len(false, 20);
+        save_config(Some(&path), &config, &THEMES, &language_keys()).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (config, warnings) = load(&saved);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.min_len, 3);
+        assert_eq!(config.head_length_for("Cyberpunk"), 3);
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        assert!(ConfigFile::parse("[matrix\n").is_err());
+        assert!(ConfigFile::parse("[matrix]\nspeed_level = \"fast\"\n").is_err());
+    }
+
+    #[test]
+    fn key_bindings_skip_what_they_cannot_bind() {
+        let file = ConfigFile::parse("[keys]\npause = [\"p\", \"F13x\"]\nfly = \"f\"\n").unwrap();
+        let (bindings, warnings) = load_key_bindings(&file);
+        assert_eq!(bindings.action(KeyCode::Char('p')), Some(Action::Pause));
+        assert_eq!(warnings, ["ignoring unknown key action 'fly'", "ignoring unknown key 'F13x' for pause"]);
+    }
+
+    #[test]
+    fn json_names_the_theme_and_language() {
+        let (config, _) = load("[matrix]\ntheme = \"Cyberpunk\"\ndensity = 0.3\n");
+        let json = config_json(&config, &THEMES, &language_keys());
+        assert_eq!(json["theme"], "Cyberpunk");
+        assert_eq!(json["language"], "Katakana");
+        assert_eq!(json["density"].to_string(), "0.3");
+        assert_eq!(json["max_len"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::color::Color;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Glyph {
+    pub char: char,
+    pub color: Color,
+    pub bold: bool,
+    pub dim: bool,
+    /// Swap the foreground and background colors.
+    pub reverse: bool,
+}
+
+pub const BLANK: Glyph = Glyph { char: ' ', color: Color::Reset, bold: false, dim: false, reverse: false };
+
+/// Placeholder for the right half of a double-width glyph; the terminal fills it
+/// when the glyph to its left is printed, so it is never printed itself.
+pub const CONTINUATION: Glyph = Glyph { char: '\0', color: Color::Reset, bold: false, dim: false, reverse: false };
+
+/// One screenful of glyphs, stored row by row.
+#[derive(Clone)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    glyphs: Vec<Glyph>,
+    /// Cells held for overlays such as the clock, which `set` leaves alone.
+    reserved: Vec<bool>,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Self {
+        let len = width as usize * height as usize;
+        Self { width, height, glyphs: vec![BLANK; len], reserved: vec![false; len] }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) {
+        *self = Self::new(width, height);
+    }
+
+    /// Blanks every cell and releases all reservations.
+    pub fn clear(&mut self) {
+        self.glyphs.fill(BLANK);
+        self.reserved.fill(false);
+    }
+
+    /// Holds the `width` x `height` area at `(x, y)` for overlays until the next
+    /// `clear`. Parts outside the frame are ignored.
+    pub fn reserve(&mut self, x: u16, y: u16, width: u16, height: u16) {
+        for row in y..y.saturating_add(height).min(self.height) {
+            for col in x..x.saturating_add(width).min(self.width) {
+                self.reserved[row as usize * self.width as usize + col as usize] = true;
+            }
+        }
+    }
+
+    /// Places `glyph` at `(x, y)`, claiming the cell to its right as well for
+    /// double-width characters. Glyphs that would not fit, or would land on a
+    /// reserved cell, are dropped.
+    pub fn set(&mut self, x: u16, y: u16, glyph: Glyph) {
+        let advance = glyph.char.width().unwrap_or(1) as u16;
+        if x + advance > self.width || y >= self.height {
+            return;
+        }
+        let i = y as usize * self.width as usize + x as usize;
+        if self.reserved[i..i + advance.max(1) as usize].contains(&true) {
+            return;
+        }
+        self.put(i, advance, glyph);
+    }
+
+    /// Writes `text` starting at `(x, y)`, reserved cells included, clipping it
+    /// at the right edge.
+    pub fn print(&mut self, x: u16, y: u16, text: &str, color: Color) {
+        if y >= self.height {
+            return;
+        }
+        let mut x = x;
+        for char in text.chars() {
+            let advance = char.width().unwrap_or(1) as u16;
+            if x + advance > self.width {
+                break;
+            }
+            let i = y as usize * self.width as usize + x as usize;
+            self.put(i, advance, Glyph { char, color, ..BLANK });
+            x += advance;
+        }
+    }
+
+    fn put(&mut self, i: usize, advance: u16, glyph: Glyph) {
+        self.glyphs[i] = glyph;
+        if advance == 2 {
+            self.glyphs[i + 1] = CONTINUATION;
+        }
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Glyph {
+        self.glyphs[y as usize * self.width as usize + x as usize]
+    }
+
+    pub fn glyphs(&self) -> &[Glyph] {
+        &self.glyphs
+    }
+}
@@ -0,0 +1,69 @@
+use crate::charset::get_random_char;
+use crate::config::Config;
+use crate::frame::{Frame, Glyph, BLANK, CONTINUATION};
+use crate::theme::ColorScheme;
+use rand::Rng;
+
+/// A few rows of interference, counting down to when they clear up.
+struct Band {
+    y: u16,
+    height: u16,
+    frames_left: u16,
+    /// Swap the colors as well as scrambling the characters.
+    invert: bool,
+}
+
+/// Random horizontal bands of signal noise laid over the rain for a few frames
+/// at a time. Glitches only touch the finished frame, never the rain itself, so
+/// everything looks normal again as soon as a band expires.
+#[derive(Default)]
+pub struct Glitch {
+    bands: Vec<Band>,
+}
+
+impl Glitch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ages the current bands and, with chance `config.glitch_rate`, starts a
+    /// new one somewhere in the `height` rows.
+    pub fn update(&mut self, config: &Config, height: u16, rng: &mut impl Rng) {
+        self.bands.retain_mut(|band| {
+            band.frames_left -= 1;
+            band.frames_left > 0
+        });
+        if height > 0 && rng.gen_bool(config.glitch_rate) {
+            self.bands.push(Band {
+                y: rng.gen_range(0..height),
+                height: rng.gen_range(1..=3),
+                frames_left: rng.gen_range(2..=6),
+                invert: rng.gen_bool(0.5),
+            });
+        }
+    }
+
+    /// Scrambles `config.glitch_intensity` of the characters in each band. In
+    /// inverted bands, head-colored glyphs also go to the fade color and all
+    /// others flash to the head color.
+    pub fn apply(&self, config: &Config, frame: &mut Frame, colors: &ColorScheme, language_key: &str, rng: &mut impl Rng) {
+        for band in &self.bands {
+            for y in band.y..band.y.saturating_add(band.height).min(frame.height()) {
+                for x in 0..frame.width() {
+                    let glyph = frame.get(x, y);
+                    if glyph == BLANK || glyph == CONTINUATION {
+                        continue;
+                    }
+                    let mut glitched = Glyph { bold: false, dim: false, reverse: false, ..glyph };
+                    if rng.gen_bool(config.glitch_intensity) {
+                        glitched.char = get_random_char(language_key, rng);
+                    }
+                    if band.invert {
+                        glitched.color = if glyph.color == colors.head { colors.fade } else { colors.head };
+                    }
+                    frame.set(x, y, glitched);
+                }
+            }
+        }
+    }
+}
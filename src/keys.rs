@@ -0,0 +1,198 @@
+use crate::config::normalize_name;
+use crossterm::event::KeyCode;
+
+/// Something a key does while the rain is running.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Quit,
+    Pause,
+    Freeze,
+    Config,
+    ThemeNext,
+    ThemePrev,
+    LanguageNext,
+    LanguagePrev,
+    Faster,
+    Slower,
+    Screenshot,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::Quit,
+        Action::Pause,
+        Action::Freeze,
+        Action::Config,
+        Action::ThemeNext,
+        Action::ThemePrev,
+        Action::LanguageNext,
+        Action::LanguagePrev,
+        Action::Faster,
+        Action::Slower,
+        Action::Screenshot,
+        Action::Help,
+    ];
+
+    /// The name used for this action in the `[keys]` table.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Pause => "pause",
+            Action::Freeze => "freeze",
+            Action::Config => "config",
+            Action::ThemeNext => "theme_next",
+            Action::ThemePrev => "theme_prev",
+            Action::LanguageNext => "language_next",
+            Action::LanguagePrev => "language_prev",
+            Action::Faster => "faster",
+            Action::Slower => "slower",
+            Action::Screenshot => "screenshot",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        let name = normalize_name(name);
+        Action::ALL.into_iter().find(|a| normalize_name(a.name()) == name)
+    }
+
+    /// What the help screen says the action does.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Pause => "Pause / resume",
+            Action::Freeze => "Freeze / resume the current frame, still following theme changes",
+            Action::Config => "Open the configuration menu",
+            Action::ThemeNext => "Next theme",
+            Action::ThemePrev => "Previous theme",
+            Action::LanguageNext => "Next language",
+            Action::LanguagePrev => "Previous language",
+            Action::Faster => "Faster",
+            Action::Slower => "Slower",
+            Action::Screenshot => "Save a screenshot to the current directory",
+            Action::Help => "Show this help",
+        }
+    }
+
+    fn default_keys(self) -> &'static [KeyCode] {
+        match self {
+            Action::Quit => &[KeyCode::Char('q'), KeyCode::Esc],
+            Action::Pause => &[KeyCode::Char(' ')],
+            Action::Freeze => &[KeyCode::Char('f')],
+            Action::Config => &[KeyCode::Char('c')],
+            Action::ThemeNext => &[KeyCode::Char('t')],
+            Action::ThemePrev => &[KeyCode::Char('T')],
+            Action::LanguageNext => &[KeyCode::Char('l')],
+            Action::LanguagePrev => &[KeyCode::Char('L')],
+            Action::Faster => &[KeyCode::Char(']')],
+            Action::Slower => &[KeyCode::Char('[')],
+            Action::Screenshot => &[KeyCode::Char('s')],
+            Action::Help => &[KeyCode::Char('?'), KeyCode::Char('h')],
+        }
+    }
+}
+
+/// Which keys trigger which actions. Each key does at most one thing.
+#[derive(Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self { bindings: Action::ALL.iter().map(|&a| (a, a.default_keys().to_vec())).collect() }
+    }
+}
+
+impl KeyBindings {
+    /// Makes `keys` the only ones for `action`. Any of them bound to another
+    /// action is taken from it, and returned with that action so the caller can
+    /// warn about the clash.
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) -> Vec<(KeyCode, Action)> {
+        let mut taken = Vec::new();
+        for (other, other_keys) in &mut self.bindings {
+            if *other == action {
+                continue;
+            }
+            other_keys.retain(|key| {
+                let clash = keys.contains(key);
+                if clash {
+                    taken.push((*key, *other));
+                }
+                !clash
+            });
+        }
+        if let Some((_, bound)) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            *bound = keys;
+        }
+        taken
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings.iter().find(|(_, keys)| keys.contains(&key)).map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings.iter().find(|(a, _)| *a == action).map_or(&[], |(_, keys)| keys)
+    }
+
+    /// The keys for `action` as the help screen shows them, e.g. `q / Esc`.
+    pub fn keys_text(&self, action: Action) -> String {
+        let names: Vec<String> = self.keys(action).iter().map(|&key| key_name(key)).collect();
+        if names.is_empty() { "(unbound)".to_string() } else { names.join(" / ") }
+    }
+}
+
+/// Reads a key name from the `[keys]` table: a single character, which is
+/// case-sensitive so `t` and `T` can do different things, or one of `space`,
+/// `esc`, `enter`, `tab`, `backspace`, `up`, `down`, `left`, `right`, `home`,
+/// `end`, `pageup`, `pagedown` and `f1`-`f12`, which are not.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key = match normalize_name(name).as_str() {
+        "space" => KeyCode::Char(' '),
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+    };
+    Some(key)
+}
+
+/// How a key is written in the help screen and in warnings.
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
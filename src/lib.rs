@@ -0,0 +1,53 @@
+//! Matrix digital rain.
+//!
+//! The simulation is independent of any terminal: [`Rain`] advances a field of
+//! falling [`Column`]s and draws them into a [`Frame`], and a [`Renderer`] puts
+//! that frame on screen. [`TerminalRenderer`] is the crossterm backend used by the
+//! `rusty_matrix` binary, behind the default `terminal` feature; other front ends
+//! can supply their own or just pull frames from a [`Simulation`]. The `wasm`
+//! feature adds one for `<canvas>`, in the `web` module, the `audio` feature
+//! lets the rain react to sound, and the `backdrop` feature lets it fall over a
+//! picture.
+
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "backdrop")]
+pub mod backdrop;
+pub mod baseline;
+pub mod charset;
+pub mod clockface;
+pub mod color;
+pub mod config;
+#[cfg(feature = "terminal")]
+pub mod config_file;
+pub mod frame;
+pub mod glitch;
+#[cfg(feature = "terminal")]
+pub mod keys;
+pub mod message;
+#[cfg(feature = "terminal")]
+pub mod monitor;
+pub mod overlay;
+pub mod rain;
+pub mod record;
+pub mod reveal;
+pub mod render;
+pub mod screenshot;
+pub mod simulation;
+pub mod stats;
+pub mod theme;
+pub mod wave;
+#[cfg(feature = "wasm")]
+pub mod web;
+
+pub use charset::{get_random_char, slot_width, language_keys, CharSet, ALL_CHAR_SETS};
+pub use config::Config;
+pub use color::Color;
+pub use frame::{Frame, Glyph};
+pub use message::Message;
+pub use rain::{Cell, Column, Depth, Direction, Rain};
+pub use render::{ColorMode, Renderer};
+pub use simulation::Simulation;
+#[cfg(feature = "terminal")]
+pub use render::TerminalRenderer;
+pub use theme::{ColorScheme, THEMES};
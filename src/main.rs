@@ -1,301 +1,2075 @@
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode},
-    execute,
-    style::{Color, Print, SetForegroundColor},
-    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEventKind},
+    execute, queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
-use rand::Rng;
-use std::collections::HashMap;
-use std::io::{stdout, Write};
-use std::time::Duration;
-
-// --- Character Generation ---
-static ALL_CHAR_SETS: once_cell::sync::Lazy<HashMap<String, Vec<char>>> = once_cell::sync::Lazy::new(|| {
-    let mut map = HashMap::new();
-
-    // English Character Set
-    let mut english_chars = Vec::new();
-    english_chars.extend('a'..='z');
-    english_chars.extend('A'..='Z');
-    english_chars.extend('0'..='9');
-    english_chars.extend("!@#$%^&*()_+-=[]{}|;:',.<>/?`~".chars());
-    map.insert("English".to_string(), english_chars);
-
-    // Traditional Chinese Character Set (Common CJK Unified Ideographs)
-    let mut traditional_chinese_chars = Vec::new();
-    for i in 0x4E00..=0x9FA5 {
-        if let Some(c) = std::char::from_u32(i) {
-            traditional_chinese_chars.push(c);
-        }
-    }
-    map.insert("Traditional Chinese".to_string(), traditional_chinese_chars);
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "audio")]
+use rusty_matrix::audio::AudioReactor;
+#[cfg(feature = "backdrop")]
+use rusty_matrix::backdrop::{self, Backdrop, Protocol};
+use rusty_matrix::baseline::Baseline;
+use rusty_matrix::charset::{
+    char_set_from_text, find_language, get_random_char, language_keys, layout_slot_width, mix_keys,
+    register_custom_char_set, CUSTOM_CHAR_SET,
+};
+use rusty_matrix::clockface::{ClockFace, FaceStyle, MAX_FACE_SIZE};
+use rusty_matrix::color::to_rgb;
+use rusty_matrix::config::{
+    normalize_name, BrightTrail, Config, Spacing, MAX_DROPS, MAX_FPS, MAX_GHOST_FRAMES, MAX_HEAD_FADE_IN, MAX_WIND, SPEED_DURATIONS,
+};
+use rusty_matrix::config_file::{
+    color_mode_names, config_json, corner_names, depth_names, direction_names, face_style_names, load_config,
+    load_key_bindings, save_config, screenshot_format_names, spacing_names, validate_head_length, validate_len_range,
+    validate_speed_jitter, wave_target_names, ConfigFile,
+};
+use rusty_matrix::keys::{key_name, Action, KeyBindings};
+use rusty_matrix::monitor::CpuMonitor;
+use rusty_matrix::overlay::{self, Corner};
+use rusty_matrix::record::Recorder;
+use rusty_matrix::render::SetColor;
+use rusty_matrix::screenshot::{self, ScreenshotFormat};
+use rusty_matrix::simulation::{self, Effects, Simulation};
+use rusty_matrix::stats::FrameStats;
+use rusty_matrix::theme::{
+    find_theme, format_hex_color, parse_hex_color, ColorScheme, CUSTOM_THEME, LIGHT_THEME, THEMES,
+};
+use rusty_matrix::wave::{Wave, WaveTarget};
+use rusty_matrix::{ColorMode, Depth, Direction, Frame, Rain, Renderer, TerminalRenderer};
+use std::io::{stdout, BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
 
-    // Simplified Chinese Character Set (Broader CJK Unified Ideographs)
-    let mut simplified_chinese_chars = Vec::new();
-    for i in 0x4E00..=0x9FFF {
-        if let Some(c) = std::char::from_u32(i) {
-            simplified_chinese_chars.push(c);
-        }
-    }
-    map.insert("Simplified Chinese".to_string(), simplified_chinese_chars);
+/// How long a status line such as "Saved screenshot" stays up.
+const STATUS_DURATION: Duration = Duration::from_secs(3);
 
-    map
-});
+/// How bright the rain stays behind the pause message.
+const PAUSE_BRIGHTNESS: f32 = 0.3;
 
-fn get_random_char(language_key: &str) -> char {
-    let mut rng = rand::thread_rng();
-    let char_set = ALL_CHAR_SETS.get(language_key).unwrap();
-    char_set[rng.gen_range(0..char_set.len())]
-}
+/// Default seconds `--art-file` is held, and then takes to melt.
+const ART_HOLD_SECONDS: f64 = 3.0;
+const ART_MELT_SECONDS: f64 = 2.0;
 
-// --- Configuration & State ---
-#[derive(Clone, Copy)]
-struct ColorScheme {
-    head: Color,
-    trail: Color,
-    fade: Color,
-}
+/// Most cells the rain is laid out over without `--force`. Each screen cell
+/// takes up to 41 bytes: a 12-byte `Glyph` in the frame and another in the
+/// renderer's copy of the screen, a byte marking it reserved for overlays, and
+/// a 16-byte `Cell` when a column rains through it. So this caps the rain at
+/// about 40 MB.
+const MAX_CELLS: u32 = 1_000_000;
+
+/// Bytes the rain can take per screen cell, as worked out for `MAX_CELLS`.
+const BYTES_PER_CELL: u32 = 41;
+
+/// Area `--dump` draws when there's no `--size` and no terminal to measure.
+const DUMP_SIZE: (u16, u16) = (80, 24);
+
+/// Save and restore the terminal's title on xterm's title stack, so the one
+/// shown before `--set-title` comes back on exit.
+const TITLE_PUSH: &str = "\x1b[22;0t";
+const TITLE_POP: &str = "\x1b[23;0t";
+
+/// Whether `TITLE_PUSH` was sent, so that any way out pops it again.
+static TITLE_PUSHED: AtomicBool = AtomicBool::new(false);
 
-const THEMES: [ColorScheme; 4] = [
-    ColorScheme { head: Color::White,   trail: Color::Green,      fade: Color::DarkGreen },
-    ColorScheme { head: Color::White,   trail: Color::Blue,       fade: Color::DarkBlue },
-    ColorScheme { head: Color::White,   trail: Color::Red,        fade: Color::DarkRed },
-    ColorScheme { head: Color::Cyan,    trail: Color::Magenta,    fade: Color::DarkMagenta },
-];
+/// Whether the rain is drawn on the main screen under `--no-altscreen`, so
+/// that the way out leaves the last frame there instead of leaving the
+/// alternate screen.
+static MAIN_SCREEN: AtomicBool = AtomicBool::new(false);
 
-struct Config {
-    theme_index: usize,
-    speed_level: usize, // 1-10
-    language_index: usize,
+/// Whether a kitty backdrop was sent, so that any way out deletes it again.
+#[cfg(feature = "backdrop")]
+static KITTY_BACKDROP: AtomicBool = AtomicBool::new(false);
+
+/// Longest the main loop waits for input before checking whether a frame is due.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Entries of the pause menu, in the order shown.
+#[derive(Clone, Copy, PartialEq)]
+enum PauseChoice {
+    Resume,
+    Config,
+    Quit,
 }
 
-const SPEED_DURATIONS: [u64; 10] = [100, 88, 76, 64, 52, 40, 33, 28, 24, 20];
+impl PauseChoice {
+    const ALL: [PauseChoice; 3] = [PauseChoice::Resume, PauseChoice::Config, PauseChoice::Quit];
+
+    fn label(self) -> &'static str {
+        match self {
+            PauseChoice::Resume => "Resume",
+            PauseChoice::Config => "Open config",
+            PauseChoice::Quit => "Quit",
+        }
+    }
+}
 
+// --- Application State ---
 enum AppState {
     Matrix,
     Paused,
     Config,
+    Help,
+    ThemeEditor,
 }
 
-// --- Cell & Column Structures ---
-#[derive(Clone)]
-struct Cell {
-    char: char,
-    color: Color,
-    lifetime: i16,
+/// Names of the colors `ThemeEditor` edits, in the order it lists them.
+const EDITOR_COLORS: [&str; 3] = ["Head", "Trail", "Fade"];
+const EDITOR_CHANNELS: [&str; 3] = ["R", "G", "B"];
+
+/// How far one +/- press moves a color channel.
+const EDITOR_STEP: u8 = 5;
+
+/// Edits the head, trail and fade colors of a scheme one RGB channel at a
+/// time, while the rain keeps falling in the colors being edited.
+struct ThemeEditor {
+    scheme: ColorScheme,
+    /// Index into `EDITOR_COLORS`.
+    color: usize,
+    /// Index into `EDITOR_CHANNELS`.
+    channel: usize,
 }
 
-impl Default for Cell {
-    fn default() -> Self {
-        Self { char: ' ', color: Color::Black, lifetime: 0 }
+impl ThemeEditor {
+    /// Starts from `base`, as a custom theme.
+    fn new(base: &ColorScheme) -> Self {
+        let rgb = |color| {
+            let (r, g, b) = to_rgb(color).unwrap_or((255, 255, 255));
+            Color::Rgb { r, g, b }
+        };
+        let scheme = ColorScheme {
+            name: CUSTOM_THEME,
+            head: rgb(base.head),
+            trail: rgb(base.trail),
+            fade: rgb(base.fade),
+            background: base.background,
+        };
+        Self { scheme, color: 0, channel: 0 }
     }
-}
 
-struct Column {
-    x: u16,
-    cells: Vec<Cell>,
-    head: i16,
-    len: i16,
-    speed: i16,
-    counter: i16,
-}
+    fn colors(&self) -> [Color; 3] {
+        [self.scheme.head, self.scheme.trail, self.scheme.fade]
+    }
+
+    fn adjust(&mut self, up: bool) {
+        let color = match self.color {
+            0 => &mut self.scheme.head,
+            1 => &mut self.scheme.trail,
+            _ => &mut self.scheme.fade,
+        };
+        if let Color::Rgb { r, g, b } = color {
+            let value = match self.channel {
+                0 => r,
+                1 => g,
+                _ => b,
+            };
+            *value = if up { value.saturating_add(EDITOR_STEP) } else { value.saturating_sub(EDITOR_STEP) };
+        }
+    }
 
-impl Column {
-    fn new(x: u16, height: u16) -> Self {
-        let mut rng = rand::thread_rng();
-        Self {
-            x,
-            cells: vec![Cell::default(); height as usize],
-            head: -1,
-            len: rng.gen_range(5..=height as i16 / 2),
-            speed: rng.gen_range(1..=4),
-            counter: 0,
+    fn text(&self) -> String {
+        let mut text = String::from("Theme Editor\n");
+        for (i, (name, color)) in EDITOR_COLORS.iter().zip(self.colors()).enumerate() {
+            let (r, g, b) = to_rgb(color).unwrap_or_default();
+            let marker = if i == self.color { '>' } else { ' ' };
+            let mut line = format!("{} {:<6}", marker, name);
+            for (c, (channel, value)) in EDITOR_CHANNELS.iter().zip([r, g, b]).enumerate() {
+                if i == self.color && c == self.channel {
+                    line.push_str(&format!(" [{} {:>3}]", channel, value));
+                } else {
+                    line.push_str(&format!("  {} {:>3} ", channel, value));
+                }
+            }
+            line.push_str(&format!("  {}", format_hex_color(color).unwrap_or_default()));
+            text.push_str(&line);
+            text.push('\n');
         }
+        text.push_str("Up/Down: color  Left/Right: channel  +/-: adjust\nEnter: use as the Custom theme  Esc: cancel");
+        text
     }
+}
+
+/// The mouse's part in the help screen, after the bound keys.
+const MOUSE_HELP: (&str, &str) = ("Click / drag", "Start drops under the pointer (with --mouse)");
 
-    fn reset(&mut self) {
-        let mut rng = rand::thread_rng();
-        let height = self.cells.len() as i16;
-        self.head = -1;
-        self.len = rng.gen_range(5..=height / 2);
-        self.speed = rng.gen_range(1..=4);
-        self.counter = 0;
+// --- Config File ---
+/// `--config` (or `$RUSTY_MATRIX_CONFIG`, which stands for it) if given,
+/// otherwise `~/.config/rusty_matrix/config.toml`.
+fn config_path(cli: &Cli) -> Option<PathBuf> {
+    if let Some(path) = &cli.config {
+        return Some(path.clone());
     }
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config").join("rusty_matrix").join("config.toml"))
+}
 
-    fn update(&mut self, colors: &ColorScheme, language_key: &str) {
-        self.counter += 1;
-        if self.counter < self.speed {
-            return;
+/// The built-in themes plus a "Custom" one when colors were given on the command
+/// line (which wins) or in `[theme.custom]`.
+fn build_themes(cli: &Cli, file: &ConfigFile) -> Vec<ColorScheme> {
+    let mut themes = THEMES.to_vec();
+    if let (Some(head), Some(trail), Some(fade)) = (cli.head, cli.trail, cli.fade) {
+        themes.push(ColorScheme { name: CUSTOM_THEME, head, trail, fade, background: None });
+    } else if let Some(custom) = file.custom_theme() {
+        match custom {
+            Ok(scheme) => themes.push(scheme),
+            Err(e) => eprintln!("warning: ignoring [theme.custom]: {}", e),
         }
-        self.counter = 0;
+    }
+    themes
+}
+
+// --- Command Line ---
+#[derive(Parser)]
+#[command(version, about = "Matrix digital rain in your terminal")]
+struct Cli {
+    /// Color theme (see the list below)
+    #[arg(long)]
+    theme: Option<String>,
+
+    /// Dark rain fading into a light background, for light terminals; the
+    /// Daylight theme
+    #[arg(long, conflicts_with = "theme")]
+    light: bool,
+
+    /// The look of the film in one go: half-width Katakana falling in green
+    /// gradients under white heads, with glints, flickering trails and
+    /// `--mirror`; other options still override it
+    #[arg(long)]
+    classic: bool,
+
+    /// Animation speed level
+    #[arg(long, value_name = "1-10", value_parser = parse_speed)]
+    speed: Option<usize>,
+
+    /// Target frames per second, 1-120; overrides --speed
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=MAX_FPS as i64))]
+    fps: Option<u32>,
+
+    /// Draw at most this many frames per second, 1-120, while the rain keeps
+    /// moving at its own speed
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..=MAX_FPS as i64))]
+    max_fps: Option<u32>,
+
+    /// Character set (see the list below)
+    #[arg(long = "lang")]
+    language: Option<String>,
+
+    /// Give each column a character set picked from this comma-separated list,
+    /// or from every set with 'all', so several scripts rain at once
+    #[arg(long, value_name = "LANGS", value_delimiter = ',')]
+    mix: Option<Vec<String>>,
+
+    /// Head color of a custom theme, as #RRGGBB
+    #[arg(long, value_parser = parse_hex_color, requires_all = ["trail", "fade"])]
+    head: Option<Color>,
+
+    /// Trail color of a custom theme, as #RRGGBB
+    #[arg(long, value_parser = parse_hex_color, requires_all = ["head", "fade"])]
+    trail: Option<Color>,
+
+    /// Fade color of a custom theme, as #RRGGBB
+    #[arg(long, value_parser = parse_hex_color, requires_all = ["head", "trail"])]
+    fade: Option<Color>,
+
+    /// Number of distinct shades in gradient trails on truecolor terminals
+    /// [default: one per cell]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(2..))]
+    gradient_steps: Option<u16>,
+
+    /// Shortest drop length, in cells
+    #[arg(long, value_name = "CELLS")]
+    min_len: Option<u16>,
+
+    /// Longest drop length, in cells [default: half the terminal height]
+    #[arg(long, value_name = "CELLS")]
+    max_len: Option<u16>,
+
+    /// Fraction of column slots that carry drops, 0.0-1.0 [default: 0.5]
+    #[arg(long, value_parser = parse_density)]
+    density: Option<f32>,
+
+    /// Cells from one column to the next, never less than the glyph width: glyph, 1 or 2;
+    /// overrides any per-set spacing in the config file [default: glyph]
+    #[arg(long, value_parser = parse_spacing)]
+    spacing: Option<Spacing>,
+
+    /// Per-frame chance that a trail character changes, 0.0-1.0 [default: 0.02]
+    #[arg(long, value_name = "RATE", value_parser = parse_probability)]
+    mutation_rate: Option<f64>,
+
+    /// Fewest frames a column rests before starting another drop [default: 0]
+    #[arg(long, value_name = "FRAMES")]
+    idle_min: Option<u16>,
+
+    /// Most frames a column rests before starting another drop [default: 30]
+    #[arg(long, value_name = "FRAMES")]
+    idle_max: Option<u16>,
+
+    /// Fewest cells above the edge a new drop starts [default: 0]
+    #[arg(long, value_name = "CELLS")]
+    start_offset_min: Option<u16>,
+
+    /// Most cells above the edge a new drop starts, so heads enter at staggered times [default: 20]
+    #[arg(long, value_name = "CELLS")]
+    start_offset_max: Option<u16>,
+
+    /// Most drops falling in one column at once, 1-8 [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=MAX_DROPS as i64))]
+    max_drops: Option<u16>,
+
+    /// Most columns raining at once across the screen, for a sparse field
+    /// where new drops start only as others finish [default: no limit]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_active: Option<u32>,
+
+    /// Per-frame chance that a resting column starts a new drop once its idle time is up, 0.0-1.0 [default: 1]
+    #[arg(long, value_name = "CHANCE", value_parser = parse_probability)]
+    spawn_chance: Option<f64>,
+
+    /// Frames a new head takes to fade in from black, 0-10; needs truecolor [default: 0]
+    #[arg(long, value_name = "FRAMES", value_parser = clap::value_parser!(u16).range(0..=MAX_HEAD_FADE_IN as i64))]
+    head_fade_in: Option<u16>,
+
+    /// Fewest frames a column waits between moves, at least 1 [default: 1]
+    #[arg(long, value_name = "FRAMES")]
+    speed_jitter_min: Option<u16>,
+
+    /// Most frames a column waits between moves [default: 4]
+    #[arg(long, value_name = "FRAMES")]
+    speed_jitter_max: Option<u16>,
+
+    /// Move drops by fractions of a cell, at any speed in the jitter range; needs truecolor
+    #[arg(long)]
+    smooth: bool,
+
+    /// Chance that a new head character glints bright white, 0.0-1.0 [default: 0.02]
+    #[arg(long, value_name = "RATE", value_parser = parse_probability)]
+    glint_rate: Option<f64>,
+
+    /// Chance that a new drop is drawn bold, and a little brighter in
+    /// truecolor, all the way down, 0.0-1.0 [default: 0.1]
+    #[arg(long, value_name = "CHANCE", value_parser = parse_probability)]
+    bold_drop_chance: Option<f64>,
+
+    /// Cells at the front of each drop, counting the head, drawn in the head
+    /// color; at most --min-len, and overrides any per-theme head length in the
+    /// config file [default: 1]
+    #[arg(long, value_name = "CELLS")]
+    head_length: Option<u16>,
+
+    /// Cells at the front of each drop, counting the head, drawn in the bright trail color
+    #[arg(long, value_name = "CELLS", conflicts_with = "bright_trail_fraction")]
+    bright_trail: Option<u16>,
+
+    /// Like --bright-trail, but as a fraction of the cells behind each head, 0.0-1.0 [default: 0.25]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_density)]
+    bright_trail_fraction: Option<f32>,
+
+    /// Which way the rain travels: down, up, left or right [default: down]
+    #[arg(long, value_parser = parse_direction)]
+    direction: Option<Direction>,
+
+    /// Dim drops by speed for a sense of depth: fast-bright, fast-dim or off; needs truecolor [default: off]
+    #[arg(long, value_parser = parse_depth)]
+    depth: Option<Depth>,
+
+    /// Degrees the Rainbow theme's hue shifts each frame, 0-360 [default: 1]
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rainbow_speed)]
+    rainbow_speed: Option<f32>,
+
+    /// Slowly turn the whole palette of any theme around the color wheel,
+    /// keeping head, trail and fade in step; needs truecolor
+    #[arg(long)]
+    hue_shift: bool,
+
+    /// Degrees a second --hue-shift turns the palette, 0-360 (implies
+    /// --hue-shift) [default: 6]
+    #[arg(long, value_name = "DEGREES", value_parser = parse_rainbow_speed)]
+    hue_shift_speed: Option<f32>,
+
+    /// Slots the wind sways the rain either side of its course, 0-100 [default: 0]
+    #[arg(long, value_name = "SLOTS", value_parser = parse_wind)]
+    wind: Option<f32>,
+
+    /// Slots per second the wind steadily blows the rain sideways, -100-100 [default: 0]
+    #[arg(long, value_name = "SLOTS", value_parser = parse_wind_bias, allow_negative_numbers = true)]
+    wind_bias: Option<f32>,
+
+    /// Seconds for one full sway of the wind [default: 10]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_wind_period)]
+    wind_period: Option<f32>,
+
+    /// Text for the rain to spell out now and then, e.g. "WAKE UP NEO"
+    #[arg(long, value_name = "TEXT")]
+    message: Option<String>,
+
+    /// Seconds between appearances of the message [default: 10]
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    message_interval: Option<u64>,
+
+    /// Colors to use: truecolor, 256, 16 or mono [default: detected from the terminal]
+    #[arg(long, value_name = "MODE", value_parser = parse_color_mode)]
+    color_mode: Option<ColorMode>,
+
+    /// Background color for the rain, e.g. #001000
+    #[arg(long, value_name = "COLOR", value_parser = parse_hex_color)]
+    bg: Option<Color>,
+
+    /// Draw heads in reverse video
+    #[arg(long)]
+    inverse_heads: bool,
+
+    /// Swap brackets, some Latin letters and the digit 3 for mirrored
+    /// look-alikes; Unicode has no mirrored kana, so Katakana hardly changes
+    #[arg(long)]
+    mirror: bool,
+
+    /// Show the current time over the rain
+    #[arg(long)]
+    clock: bool,
+
+    /// Show the date next to the time (implies --clock)
+    #[arg(long)]
+    clock_date: bool,
+
+    /// Corner for the clock: top-left, top-right, bottom-left or bottom-right [default: top-right]
+    #[arg(long, value_name = "CORNER", value_parser = parse_corner)]
+    clock_corner: Option<Corner>,
+
+    /// Spell the time in large digits made of rain in the middle of the screen
+    #[arg(long)]
+    big_clock: bool,
+
+    /// Digits for the big clock: segments or blocks [default: segments] (implies --big-clock)
+    #[arg(long, value_name = "STYLE", value_parser = parse_face_style)]
+    big_clock_style: Option<FaceStyle>,
+
+    /// Size of the big clock's digits, from 1 to 4 [default: 2] (implies --big-clock)
+    #[arg(long, value_name = "SIZE", value_parser = parse_face_size)]
+    big_clock_size: Option<u16>,
+
+    /// Show seconds on the big clock (implies --big-clock)
+    #[arg(long)]
+    big_clock_seconds: bool,
+
+    /// Show frames per second and the average frame time in a top corner
+    #[arg(long)]
+    show_fps: bool,
+
+    /// Show the theme and language in the terminal's title, putting the old
+    /// title back on exit; off by default as some terminals mishandle it
+    #[arg(long)]
+    set_title: bool,
+
+    /// Move the clock, fps and other overlay text by a cell every few minutes,
+    /// to keep it from burning into OLED screens
+    #[arg(long)]
+    shift_overlays: bool,
+
+    /// Flash random bands of glitchy interference over the rain
+    #[arg(long)]
+    glitch: bool,
+
+    /// Per-frame chance of a new glitch band, 0.0-1.0 (implies --glitch) [default: 0.02]
+    #[arg(long, value_name = "RATE", value_parser = parse_probability)]
+    glitch_rate: Option<f64>,
+
+    /// Fraction of characters scrambled in a glitch band, 0.0-1.0 (implies --glitch) [default: 0.5]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_probability)]
+    glitch_intensity: Option<f64>,
+
+    /// Now and then strike a random column with a bolt of lightning that crosses
+    /// the screen in a couple of frames
+    #[arg(long)]
+    lightning: bool,
+
+    /// Per-frame chance of a lightning strike, 0.0-1.0 (implies --lightning) [default: 0.005]
+    #[arg(long, value_name = "RATE", value_parser = parse_probability)]
+    lightning_rate: Option<f64>,
+
+    /// How close to white bolts are drawn, from 0.0 for the theme's head color
+    /// to 1.0 (implies --lightning) [default: 1.0]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_density)]
+    lightning_brightness: Option<f32>,
+
+    /// Let some trail cells linger for a few frames as a very dark ghost of
+    /// their last character before clearing, softening the tail's end
+    #[arg(long)]
+    ghosts: bool,
+
+    /// Chance that a cell leaves a ghost, 0.0-1.0 (implies --ghosts) [default: 0.3]
+    #[arg(long, value_name = "CHANCE", value_parser = parse_probability)]
+    ghost_chance: Option<f64>,
+
+    /// Frames a ghost lingers, from 1 to 60 (implies --ghosts) [default: 6]
+    #[arg(long, value_name = "FRAMES", value_parser = clap::value_parser!(u8).range(1..=MAX_GHOST_FRAMES as i64))]
+    ghost_frames: Option<u8>,
+
+    /// Speed up and thicken the rain as CPU usage rises, from the configured
+    /// speed and density when idle to --cpu-speed and --cpu-density at full load
+    #[arg(long)]
+    cpu_monitor: bool,
+
+    /// Speed level at full CPU load, 1-10 (implies --cpu-monitor) [default: 10]
+    #[arg(long, value_name = "LEVEL", value_parser = parse_speed)]
+    cpu_speed: Option<usize>,
+
+    /// Density at full CPU load, 0.0-1.0 (implies --cpu-monitor) [default: 1.0]
+    #[arg(long, value_name = "DENSITY", value_parser = parse_density)]
+    cpu_density: Option<f32>,
+
+    /// Show the CPU usage in the bottom-right corner (implies --cpu-monitor)
+    #[arg(long)]
+    show_cpu: bool,
+
+    /// Pulse the rain's speed and spawn rate with the default audio input
+    /// (needs a build with the audio feature)
+    #[arg(long, conflicts_with_all = ["cpu_monitor", "cpu_speed", "cpu_density", "show_cpu"])]
+    audio: bool,
 
-        self.head += 1;
+    /// How strongly --audio reacts, 0.0-1.0 (implies --audio) [default: 0.5]
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_density)]
+    audio_reactivity: Option<f32>,
 
-        for cell in self.cells.iter_mut() {
-            if cell.lifetime > 0 {
-                cell.lifetime -= 1;
-                if cell.lifetime == 0 {
-                    cell.char = ' ';
+    /// Make the rain breathe, its spawn rate and/or speed rising and falling
+    /// on a slow sine cycle
+    #[arg(long, conflicts_with_all = ["cpu_monitor", "cpu_speed", "cpu_density", "show_cpu", "audio", "audio_reactivity"])]
+    wave: bool,
+
+    /// What --wave swells and ebbs: spawn, speed or both (implies --wave) [default: spawn]
+    #[arg(long, value_name = "TARGET", value_parser = parse_wave_target)]
+    wave_target: Option<WaveTarget>,
+
+    /// Seconds one --wave cycle takes (implies --wave) [default: 20]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_wave_period)]
+    wave_period: Option<f32>,
+
+    /// How far --wave swings either way, as a fraction of the starting value,
+    /// 0.0-1.0 (implies --wave) [default: 0.5]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_density)]
+    wave_amplitude: Option<f32>,
+
+    /// What the 's' key saves: text (characters only) or ansi (with colors) [default: ansi]
+    #[arg(long, value_name = "FORMAT", value_parser = parse_screenshot_format)]
+    screenshot_format: Option<ScreenshotFormat>,
+
+    /// Don't draw heads bold and tails dim
+    #[arg(long)]
+    no_attributes: bool,
+
+    /// Rain the characters of this UTF-8 file as the "Custom" language; a line such as "ｱ 5" makes that character 5x as common
+    #[arg(long, value_name = "PATH")]
+    charset_file: Option<PathBuf>,
+
+    /// Config file to read settings from and save them to, or - to read one
+    /// from stdin and save nothing [default: ~/.config/rusty_matrix/config.toml]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Click or drag in the terminal to start drops there
+    #[arg(long)]
+    mouse: bool,
+
+    /// Draw on the main screen instead of the alternate one, scrolling what
+    /// was there into the scrollback first, and leave the last frame on
+    /// screen on exit
+    #[arg(long)]
+    no_altscreen: bool,
+
+    /// Exit on any key press, or any mouse movement with --mouse
+    #[arg(long)]
+    screensaver: bool,
+
+    /// Exit after this long, e.g. 30s, 5m or 1h30m; 0 runs until quit [default: 0]
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+
+    /// Open by showing this text or ASCII art, then melt it into the rain
+    #[arg(long, value_name = "PATH")]
+    art_file: Option<PathBuf>,
+
+    /// Let the rain fall over this picture, a PNG (kitty only) or binary PPM, on
+    /// terminals with kitty or sixel graphics (needs a build with the backdrop feature)
+    #[arg(long, value_name = "PATH")]
+    backdrop: Option<PathBuf>,
+
+    /// Graphics protocol for --backdrop: kitty or sixel [default: guessed from the terminal]
+    #[cfg(feature = "backdrop")]
+    #[arg(long, value_name = "PROTOCOL", requires = "backdrop", value_parser = parse_backdrop_protocol)]
+    backdrop_protocol: Option<Protocol>,
+
+    /// Seconds --art-file stays on screen before it starts to melt [default: 3]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    art_hold: Option<f64>,
+
+    /// Seconds --art-file takes to melt away completely [default: 2]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    art_melt: Option<f64>,
+
+    /// Record the session to an asciinema v2 .cast file
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Seed for the random number generator, for reproducible animations
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Print the first --frames frames to stdout as text or ansi and exit,
+    /// without taking over the terminal; use with --seed and --size for
+    /// repeatable output [default: text]
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "text", value_parser = parse_screenshot_format)]
+    dump: Option<ScreenshotFormat>,
+
+    /// Number of frames --dump prints [default: 1]
+    #[arg(long, value_name = "N", requires = "dump", value_parser = clap::value_parser!(u32).range(1..))]
+    frames: Option<u32>,
+
+    /// Print the settings, after the config file, environment and command line
+    /// are merged, to stdout as JSON and exit
+    #[arg(long, conflicts_with = "dump")]
+    print_config: bool,
+
+    /// Lay the rain out at COLSxROWS, e.g. 80x24, whatever the terminal's size;
+    /// cells past its edges aren't shown [default: the terminal's size]
+    #[arg(long, value_name = "COLSxROWS", value_parser = parse_size)]
+    size: Option<(u16, u16)>,
+
+    /// Lay the rain out over more than a million cells, from --size or a huge
+    /// terminal, though it can take a lot of memory (up to 41 bytes a cell)
+    #[arg(long)]
+    force: bool,
+
+    /// Quit at once instead of fading the rain out first
+    #[arg(long)]
+    no_fade_out: bool,
+
+    /// Switch themes at once instead of cross-fading their colors
+    #[arg(long)]
+    no_theme_fade: bool,
+
+    /// Do not write menu changes back to the config file on exit
+    #[arg(long)]
+    no_save: bool,
+}
+
+fn option_list(names: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+    names.into_iter().map(|n| format!("  {}", n.as_ref())).collect::<Vec<_>>().join("\n")
+}
+
+/// Prefix of the environment variables that stand in for command-line options.
+const ENV_PREFIX: &str = "RUSTY_MATRIX_";
+
+/// Options given through the environment, one variable per long option:
+/// `RUSTY_MATRIX_THEME=Cyberpunk` acts like `--theme Cyberpunk`, and
+/// `RUSTY_MATRIX_CLOCK=1` like `--clock`. Values the option would reject are
+/// reported and skipped, and so are options that `cli_args`, the command line,
+/// gives again or conflicts with. `var` looks a variable up.
+fn env_args(
+    command: &clap::Command,
+    cli_args: &[String],
+    var: impl Fn(&str) -> Option<String>,
+    language_keys: &[String],
+) -> Vec<String> {
+    // Errors are left for the real parse; this only finds what was given.
+    let explicit = command.clone().ignore_errors(true).try_get_matches_from(cli_args).ok();
+    let given = |arg: &clap::Arg| {
+        explicit.as_ref().is_some_and(|m| m.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+    };
+    let overridden = |arg: &clap::Arg| {
+        given(arg)
+            || command.get_arguments().any(|other| {
+                given(other)
+                    && (command.get_arg_conflicts_with(arg).iter().any(|c| c.get_id() == other.get_id())
+                        || command.get_arg_conflicts_with(other).iter().any(|c| c.get_id() == arg.get_id()))
+            })
+    };
+    let mut args = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(long) = arg.get_long().filter(|long| !matches!(*long, "help" | "version")) else {
+            continue;
+        };
+        let name = format!("{}{}", ENV_PREFIX, long.to_uppercase().replace('-', "_"));
+        let Some(value) = var(&name) else {
+            continue;
+        };
+        if overridden(arg) {
+            continue;
+        }
+        // Flags, and options whose value can be left out like --dump's, take true or false.
+        let optional = arg.get_num_args().is_some_and(|range| range.min_values() == 0);
+        if !arg.get_action().takes_values() || optional {
+            match value.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => {
+                    args.push(format!("--{}", long));
+                    continue;
+                }
+                "" | "0" | "false" | "no" | "off" => continue,
+                _ if !optional => {
+                    eprintln!("warning: ignoring {}: expected true or false, got '{}'", name, value);
+                    continue;
                 }
+                _ => {}
             }
         }
-
-        for i in 0..self.cells.len() {
-            if self.cells[i].lifetime > self.len - 3 {
-                self.cells[i].color = colors.trail;
-            } else {
-                self.cells[i].color = colors.fade;
+        // Custom themes and character sets are only known later, so accept those names here.
+        let unknown = match long {
+            "theme" => find_theme(&value, &THEMES).is_none() && normalize_name(&value) != normalize_name(CUSTOM_THEME),
+            "lang" => find_language(&value, language_keys).is_none() && normalize_name(&value) != normalize_name(CUSTOM_CHAR_SET),
+            "mix" => value.split(',').any(|name| {
+                let name = normalize_name(name);
+                find_language(&name, language_keys).is_none() && name != "all" && name != normalize_name(CUSTOM_CHAR_SET)
+            }),
+            _ => false,
+        };
+        if unknown {
+            eprintln!("warning: ignoring {}: unknown value '{}'", name, value);
+            continue;
+        }
+        // Checked alone, so options that need others, like --head, are only
+        // missing those here; the full parse checks them.
+        let option = format!("--{}={}", long, value);
+        match command.clone().try_get_matches_from(["rusty_matrix", option.as_str()]) {
+            Err(e) if e.kind() != ErrorKind::MissingRequiredArgument => {
+                let reason = e.to_string();
+                let reason = reason.lines().next().unwrap_or_default().trim_start_matches("error: ");
+                eprintln!("warning: ignoring {}: {}", name, reason);
             }
+            _ => args.push(option),
         }
+    }
+    args
+}
 
-        if self.head >= 0 && self.head < self.cells.len() as i16 {
-            let head_idx = self.head as usize;
-            self.cells[head_idx] = Cell {
-                char: get_random_char(language_key),
-                color: colors.head,
-                lifetime: self.len,
-            };
-        }
+fn parse_cli(language_keys: &[String]) -> Cli {
+    let after_help = format!(
+        "Themes:\n{}\n  {} (with --head/--trail/--fade or [theme.custom])\n\nSpeeds:\n  1-{} (slowest to fastest)\n\nLanguages:\n{}\n\n\
+         Every option can also be set through the environment, e.g. {}THEME or {}CLOCK_DATE=1.\n\
+         Options given on the command line take precedence.",
+        option_list(THEMES.iter().map(|t| t.name)),
+        CUSTOM_THEME,
+        SPEED_DURATIONS.len(),
+        option_list(language_keys),
+        ENV_PREFIX,
+        ENV_PREFIX
+    );
+    let command = Cli::command().after_help(after_help).args_override_self(true);
+    let cli_args: Vec<String> = std::env::args().collect();
+    let env_args = env_args(&command, &cli_args, |name| std::env::var(name).ok(), language_keys);
+    let (program, rest) = cli_args.split_at(cli_args.len().min(1));
+    let matches = command.get_matches_from(program.iter().chain(&env_args).chain(rest));
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
 
-        if self.head >= self.cells.len() as i16 + self.len {
-            self.reset();
-        }
+fn parse_speed(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(level) if (1..=SPEED_DURATIONS.len()).contains(&level) => Ok(level),
+        _ => Err(format!("expected a speed level from 1 to {}, got '{}'", SPEED_DURATIONS.len(), s)),
     }
+}
 
-    fn draw(&self, stdout: &mut std::io::Stdout) {
-        for (y, cell) in self.cells.iter().enumerate() {
-            if cell.lifetime > 0 {
-                execute!(
-                    stdout,
-                    cursor::MoveTo(self.x * 2, y as u16),
-                    SetForegroundColor(cell.color),
-                    Print(cell.char)
-                )
-                .unwrap();
-            }
+fn parse_density(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(density) if (0.0..=1.0).contains(&density) => Ok(density),
+        _ => Err(format!("expected a number between 0.0 and 1.0, got '{}'", s)),
+    }
+}
+
+fn parse_direction(s: &str) -> Result<Direction, String> {
+    Direction::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", direction_names(), s))
+}
+
+fn parse_depth(s: &str) -> Result<Depth, String> {
+    Depth::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", depth_names(), s))
+}
+
+fn parse_spacing(s: &str) -> Result<Spacing, String> {
+    Spacing::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", spacing_names(), s))
+}
+
+#[cfg(feature = "backdrop")]
+fn backdrop_protocol_names() -> String {
+    Protocol::ALL.map(Protocol::name).join(", ")
+}
+
+#[cfg(feature = "backdrop")]
+fn parse_backdrop_protocol(s: &str) -> Result<Protocol, String> {
+    Protocol::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", backdrop_protocol_names(), s))
+}
+
+fn parse_wave_target(s: &str) -> Result<WaveTarget, String> {
+    WaveTarget::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", wave_target_names(), s))
+}
+
+fn parse_wave_period(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(seconds),
+        _ => Err(format!("expected a number of seconds above 0, got '{}'", s)),
+    }
+}
+
+fn parse_corner(s: &str) -> Result<Corner, String> {
+    Corner::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", corner_names(), s))
+}
+
+fn parse_face_style(s: &str) -> Result<FaceStyle, String> {
+    FaceStyle::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", face_style_names(), s))
+}
+
+fn parse_face_size(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(size) if (1..=MAX_FACE_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!("expected a size from 1 to {}, got '{}'", MAX_FACE_SIZE, s)),
+    }
+}
+
+fn parse_rainbow_speed(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(speed) if (0.0..=360.0).contains(&speed) => Ok(speed),
+        _ => Err(format!("expected a number of degrees between 0 and 360, got '{}'", s)),
+    }
+}
+
+fn parse_wind(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(wind) if (0.0..=MAX_WIND).contains(&wind) => Ok(wind),
+        _ => Err(format!("expected a number of slots between 0 and {}, got '{}'", MAX_WIND, s)),
+    }
+}
+
+fn parse_wind_bias(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(bias) if (-MAX_WIND..=MAX_WIND).contains(&bias) => Ok(bias),
+        _ => Err(format!("expected a number of slots per second between -{} and {}, got '{}'", MAX_WIND, MAX_WIND, s)),
+    }
+}
+
+fn parse_wind_period(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(period) if period.is_finite() && period > 0.0 => Ok(period),
+        _ => Err(format!("expected a positive number of seconds, got '{}'", s)),
+    }
+}
+
+fn parse_color_mode(s: &str) -> Result<ColorMode, String> {
+    ColorMode::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", color_mode_names(), s))
+}
+
+fn parse_screenshot_format(s: &str) -> Result<ScreenshotFormat, String> {
+    ScreenshotFormat::from_name(s).ok_or_else(|| format!("expected one of {}, got '{}'", screenshot_format_names(), s))
+}
+
+/// A size such as `80x24`, each side from 1 to 65535. Sizes over `MAX_CELLS`
+/// are turned away later, unless `--force` is given.
+fn parse_size(s: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("expected COLSxROWS with each from 1 to {}, e.g. 80x24, got '{}'", u16::MAX, s);
+    let (cols, rows) = s.trim().split_once(['x', 'X']).ok_or_else(invalid)?;
+    match (cols.trim().parse::<u16>(), rows.trim().parse::<u16>()) {
+        (Ok(cols), Ok(rows)) if cols > 0 && rows > 0 => Ok((cols, rows)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Whether a `width` x `height` area is more than `MAX_CELLS`.
+fn too_many_cells(width: u16, height: u16) -> bool {
+    width as u32 * height as u32 > MAX_CELLS
+}
+
+/// Exits unless the rain can be laid out over `width` x `height` cells: at most
+/// `MAX_CELLS` of them, or any number with `--force`.
+fn check_size(cli: &Cli, width: u16, height: u16) {
+    if cli.force || !too_many_cells(width, height) {
+        return;
+    }
+    let cells = width as u32 * height as u32;
+    eprintln!(
+        "error: {}x{} is {} cells, which could take {} MB; rusty_matrix lays out at most {} without --force",
+        width,
+        height,
+        cells,
+        cells as u64 * BYTES_PER_CELL as u64 / 1_000_000,
+        MAX_CELLS
+    );
+    std::process::exit(2);
+}
+
+/// A duration such as `90`, `30s`, `5m`, `1h30m` or `500ms`. A bare number is
+/// seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("expected a duration such as 30s, 5m or 1h30m, got '{}'", s);
+    let text = s.trim();
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    let mut total = Duration::ZERO;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let amount: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let part = match &rest[..unit] {
+            "ms" => Duration::from_millis(amount),
+            "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount * 60),
+            "h" => Duration::from_secs(amount * 3600),
+            _ => return Err(invalid()),
+        };
+        total += part;
+        rest = &rest[unit..];
+    }
+    if text.is_empty() { Err(invalid()) } else { Ok(total) }
+}
+
+fn parse_seconds(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => Ok(seconds),
+        _ => Err(format!("expected a number of seconds, got '{}'", s)),
+    }
+}
+
+fn parse_probability(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("expected a probability between 0.0 and 1.0, got '{}'", s)),
+    }
+}
+
+/// Registers the characters of `path` as the "Custom" set, exiting with an error
+/// if the file can't be read or has nothing but whitespace in it.
+fn load_charset_file(path: &std::path::Path) {
+    let result = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| char_set_from_text(&text))
+        .and_then(register_custom_char_set);
+    if let Err(e) = result {
+        eprintln!("error: could not use {} as a character set: {}", path.display(), e);
+        std::process::exit(2);
+    }
+}
+
+fn invalid_option(flag: &str, value: &str, valid: String) -> ! {
+    eprintln!("error: invalid value '{}' for {}\n\nValid values:\n{}", value, flag, valid);
+    std::process::exit(2);
+}
+
+/// Command-line flags take precedence over the config file.
+fn apply_cli(config: &mut Config, cli: &Cli, themes: &[ColorScheme], language_keys: &[String]) {
+    // Goes first so the flags below can change any part of it.
+    if cli.classic {
+        config.apply_classic(language_keys);
+    }
+    if cli.head.is_some() {
+        config.theme_index = themes.len() - 1;
+    }
+    if let Some(name) = &cli.theme {
+        config.theme_index = find_theme(name, themes)
+            .unwrap_or_else(|| invalid_option("--theme", name, option_list(themes.iter().map(|t| t.name))));
+    }
+    if cli.light
+        && let Some(index) = find_theme(LIGHT_THEME, themes)
+    {
+        config.theme_index = index;
+    }
+    if let Some(level) = cli.speed {
+        config.speed_level = level;
+        config.fps = None;
+    }
+    if cli.fps.is_some() {
+        config.fps = cli.fps;
+    }
+    if cli.max_fps.is_some() {
+        config.max_fps = cli.max_fps;
+    }
+    if cli.charset_file.is_some() {
+        config.language_index = find_language(CUSTOM_CHAR_SET, language_keys).unwrap();
+    }
+    if let Some(name) = &cli.language {
+        config.language_index = find_language(name, language_keys)
+            .unwrap_or_else(|| invalid_option("--lang", name, option_list(language_keys)));
+    }
+    if let Some(names) = &cli.mix {
+        config.mix = mix_keys(names, language_keys).unwrap_or_else(|name| {
+            invalid_option("--mix", &name, option_list(std::iter::once("all").chain(language_keys.iter().map(String::as_str))))
+        });
+    }
+    if let Some(steps) = cli.gradient_steps {
+        config.gradient_steps = Some(steps as usize);
+    }
+    if cli.min_len.is_some() || cli.max_len.is_some() {
+        let min_len = cli.min_len.unwrap_or(config.min_len);
+        let max_len = cli.max_len.or(config.max_len);
+        if let Err(e) = validate_len_range(min_len, max_len) {
+            eprintln!("error: invalid --min-len/--max-len: {}", e);
+            std::process::exit(2);
+        }
+        config.min_len = min_len;
+        config.max_len = max_len;
+    }
+    if let Some(density) = cli.density {
+        config.density = density;
+    }
+    if let Some(spacing) = cli.spacing {
+        config.spacing = spacing;
+        config.set_spacing.clear();
+    }
+    if let Some(rate) = cli.mutation_rate {
+        config.mutation_rate = rate;
+    }
+    if cli.idle_min.is_some() || cli.idle_max.is_some() {
+        let idle_min = cli.idle_min.unwrap_or(config.idle_min);
+        let idle_max = cli.idle_max.unwrap_or(config.idle_max);
+        if idle_min > idle_max {
+            eprintln!("error: invalid --idle-min/--idle-max: idle_min {} is greater than idle_max {}", idle_min, idle_max);
+            std::process::exit(2);
+        }
+        config.idle_min = idle_min;
+        config.idle_max = idle_max;
+    }
+    if cli.start_offset_min.is_some() || cli.start_offset_max.is_some() {
+        let min = cli.start_offset_min.unwrap_or(config.start_offset_min);
+        let max = cli.start_offset_max.unwrap_or(config.start_offset_max);
+        if min > max {
+            eprintln!(
+                "error: invalid --start-offset-min/--start-offset-max: start_offset_min {} is greater than start_offset_max {}",
+                min, max
+            );
+            std::process::exit(2);
+        }
+        config.start_offset_min = min;
+        config.start_offset_max = max;
+    }
+    if let Some(frames) = cli.head_fade_in {
+        config.head_fade_in = frames;
+    }
+    if let Some(chance) = cli.spawn_chance {
+        config.spawn_chance = chance;
+    }
+    if let Some(max_active) = cli.max_active {
+        config.max_active = Some(max_active as usize);
+    }
+    if let Some(max_drops) = cli.max_drops {
+        config.max_drops = max_drops as usize;
+    }
+    if cli.speed_jitter_min.is_some() || cli.speed_jitter_max.is_some() {
+        let jitter_min = cli.speed_jitter_min.unwrap_or(config.speed_jitter_min);
+        let jitter_max = cli.speed_jitter_max.unwrap_or(config.speed_jitter_max);
+        if let Err(e) = validate_speed_jitter(jitter_min, jitter_max) {
+            eprintln!("error: invalid --speed-jitter-min/--speed-jitter-max: {}", e);
+            std::process::exit(2);
+        }
+        config.speed_jitter_min = jitter_min;
+        config.speed_jitter_max = jitter_max;
+    }
+    if let Some(cells) = cli.bright_trail {
+        config.bright_trail = BrightTrail::Cells(cells);
+    }
+    if let Some(fraction) = cli.bright_trail_fraction {
+        config.bright_trail = BrightTrail::Fraction(fraction);
+    }
+    if cli.smooth {
+        config.smooth_motion = true;
+    }
+    if let Some(rate) = cli.glint_rate {
+        config.glint_rate = rate;
+    }
+    if let Some(chance) = cli.bold_drop_chance {
+        config.bold_drop_chance = chance;
+    }
+    if let Some(len) = cli.head_length {
+        if let Err(e) = validate_head_length(len, config.min_len) {
+            eprintln!("error: invalid --head-length: {}", e);
+            std::process::exit(2);
         }
+        config.head_length = len;
+        config.theme_head_length.clear();
+    }
+    if let Some(direction) = cli.direction {
+        config.direction = direction;
+    }
+    if let Some(depth) = cli.depth {
+        config.depth = depth;
+    }
+    if let Some(wind) = cli.wind {
+        config.wind = wind;
+    }
+    if let Some(bias) = cli.wind_bias {
+        config.wind_bias = bias;
+    }
+    if let Some(period) = cli.wind_period {
+        config.wind_period = period;
+    }
+    if let Some(speed) = cli.rainbow_speed {
+        config.rainbow_speed = speed;
+    }
+    if cli.hue_shift || cli.hue_shift_speed.is_some() {
+        config.hue_shift = true;
+    }
+    if let Some(speed) = cli.hue_shift_speed {
+        config.hue_shift_speed = speed;
+    }
+    if cli.message.is_some() {
+        config.message = cli.message.clone();
+    }
+    if let Some(interval) = cli.message_interval {
+        config.message_interval = interval;
+    }
+    if cli.clock || cli.clock_date {
+        config.clock = true;
+    }
+    if cli.clock_date {
+        config.clock_date = true;
+    }
+    if let Some(corner) = cli.clock_corner {
+        config.clock_corner = corner;
+    }
+    if cli.big_clock || cli.big_clock_style.is_some() || cli.big_clock_size.is_some() || cli.big_clock_seconds {
+        config.big_clock = true;
+    }
+    if let Some(style) = cli.big_clock_style {
+        config.big_clock_style = style;
+    }
+    if let Some(size) = cli.big_clock_size {
+        config.big_clock_size = size;
+    }
+    if cli.big_clock_seconds {
+        config.big_clock_seconds = true;
+    }
+    if cli.show_fps {
+        config.show_fps = true;
+    }
+    if cli.set_title {
+        config.set_title = true;
+    }
+    if cli.shift_overlays {
+        config.shift_overlays = true;
+    }
+    if cli.color_mode.is_some() {
+        config.color_mode = cli.color_mode;
+    }
+    if cli.bg.is_some() {
+        config.background = cli.bg;
+    }
+    if cli.inverse_heads {
+        config.inverse_heads = true;
     }
+    if cli.mirror {
+        config.mirror = true;
+    }
+    if cli.glitch || cli.glitch_rate.is_some() || cli.glitch_intensity.is_some() {
+        config.glitch = true;
+    }
+    if let Some(rate) = cli.glitch_rate {
+        config.glitch_rate = rate;
+    }
+    if let Some(intensity) = cli.glitch_intensity {
+        config.glitch_intensity = intensity;
+    }
+    if cli.lightning || cli.lightning_rate.is_some() || cli.lightning_brightness.is_some() {
+        config.lightning = true;
+    }
+    if let Some(rate) = cli.lightning_rate {
+        config.lightning_rate = rate;
+    }
+    if let Some(brightness) = cli.lightning_brightness {
+        config.lightning_brightness = brightness;
+    }
+    if cli.ghosts || cli.ghost_chance.is_some() || cli.ghost_frames.is_some() {
+        config.ghosts = true;
+    }
+    if let Some(chance) = cli.ghost_chance {
+        config.ghost_chance = chance;
+    }
+    if let Some(frames) = cli.ghost_frames {
+        config.ghost_frames = frames;
+    }
+    if cli.cpu_monitor || cli.cpu_speed.is_some() || cli.cpu_density.is_some() || cli.show_cpu {
+        config.cpu_monitor = true;
+    }
+    if let Some(level) = cli.cpu_speed {
+        config.cpu_speed_level = level;
+    }
+    if let Some(density) = cli.cpu_density {
+        config.cpu_density = density;
+    }
+    if cli.show_cpu {
+        config.show_cpu = true;
+    }
+    if cli.audio || cli.audio_reactivity.is_some() {
+        config.audio = true;
+    }
+    if let Some(reactivity) = cli.audio_reactivity {
+        config.audio_reactivity = reactivity;
+    }
+    if cli.wave || cli.wave_target.is_some() || cli.wave_period.is_some() || cli.wave_amplitude.is_some() {
+        config.wave = true;
+    }
+    if let Some(target) = cli.wave_target {
+        config.wave_target = target;
+    }
+    if let Some(period) = cli.wave_period {
+        config.wave_period = period;
+    }
+    if let Some(amplitude) = cli.wave_amplitude {
+        config.wave_amplitude = amplitude;
+    }
+    if let Some(format) = cli.screenshot_format {
+        config.screenshot_format = format;
+    }
+    if cli.no_attributes {
+        config.attributes = false;
+    }
+    if cli.seed.is_some() {
+        config.seed = cli.seed;
+    }
+    if cli.no_fade_out {
+        config.fade_out = false;
+    }
+    if cli.no_theme_fade {
+        config.theme_fade = false;
+    }
+    if cli.no_save {
+        config.save_on_exit = false;
+    }
+}
+
+/// Steps `index` one place forward or back through `len` items, wrapping around.
+fn cycle(index: usize, len: usize, forward: bool) -> usize {
+    if forward {
+        (index + 1) % len
+    } else {
+        (index + len - 1) % len
+    }
+}
+
+/// Re-lays out the rain after a resize or a change of language, density or direction.
+fn relayout(rain: &mut Rain, width: u16, height: u16, config: &Config, language_keys: &[String]) {
+    rain.resize(width, height, layout_slot_width(config, language_keys), config);
+}
+
+/// Writes `frame` to a timestamped file in the current directory and returns
+/// its name.
+fn save_screenshot(frame: &Frame, format: ScreenshotFormat, color_mode: ColorMode) -> std::io::Result<String> {
+    let contents = match format {
+        ScreenshotFormat::Text => screenshot::to_text(frame),
+        // The file is asked for with colors, even if this terminal shows none.
+        ScreenshotFormat::Ansi if color_mode == ColorMode::Mono => screenshot::to_ansi(frame, ColorMode::Ansi256),
+        ScreenshotFormat::Ansi => screenshot::to_ansi(frame, color_mode),
+    };
+    let name = format!("rusty_matrix-{}.{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), format.extension());
+    std::fs::write(&name, contents)?;
+    Ok(name)
+}
+
+/// Runs the rain for `--dump` without the terminal, writing each frame to
+/// `out` under a `--- frame N ---` line. Time is simulated at the frame rate,
+/// so messages and art come out the same on every run; overlays that show
+/// the clock or system state are left out for the same reason.
+fn dump_frames(cli: &Cli, config: &Config, themes: &[ColorScheme], art: Option<&str>, out: &mut impl Write) -> std::io::Result<()> {
+    let format = cli.dump.unwrap_or(ScreenshotFormat::Text);
+    let (width, height) = cli
+        .size
+        .unwrap_or_else(|| terminal::size().ok().filter(|&(w, h)| w > 0 && h > 0).unwrap_or(DUMP_SIZE));
+    check_size(cli, width, height);
+    let color_mode = match config.color_mode.unwrap_or_else(ColorMode::detect) {
+        ColorMode::Mono if format == ScreenshotFormat::Ansi => ColorMode::Ansi256,
+        mode => mode,
+    };
+    let mut simulation = Simulation::new(width, height, config.clone());
+    simulation.colors = themes[config.theme_index];
+    let rain = simulation.rain_mut();
+    rain.truecolor = color_mode == ColorMode::TrueColor;
+    rain.dissolve = matches!(color_mode, ColorMode::TrueColor | ColorMode::Ansi256);
+    if let Some(art) = art {
+        let hold = Duration::from_secs_f64(cli.art_hold.unwrap_or(ART_HOLD_SECONDS));
+        let melt = Duration::from_secs_f64(cli.art_melt.unwrap_or(ART_MELT_SECONDS));
+        simulation.reveal(art, hold, melt);
+    }
+
+    for i in 0..cli.frames.unwrap_or(1) {
+        let frame = simulation.next_frame();
+        let contents = match format {
+            ScreenshotFormat::Text => screenshot::to_text(frame),
+            ScreenshotFormat::Ansi => screenshot::to_ansi(frame, color_mode),
+        };
+        writeln!(out, "--- frame {} ---", i + 1)?;
+        out.write_all(contents.as_bytes())?;
+    }
+    out.flush()
 }
 
 // --- UI Drawing ---
-fn draw_ui(text: &str, stdout: &mut std::io::Stdout, clear_screen: bool) -> std::io::Result<()> {
+fn help_text(bindings: &KeyBindings) -> String {
+    let mut lines: Vec<(String, &str)> =
+        Action::ALL.iter().map(|&action| (bindings.keys_text(action), action.description())).collect();
+    lines.push((MOUSE_HELP.0.to_string(), MOUSE_HELP.1));
+    let key_width = lines.iter().map(|(keys, _)| keys.width()).max().unwrap_or(0);
+    let mut text = String::from("Key Bindings\n\n");
+    for (keys, action) in &lines {
+        text += &format!("  {:<width$}  {}\n", keys, action, width = key_width);
+    }
+    text += "\nPress any key to return to the matrix";
+    text
+}
+
+/// The pause menu with the entry at `choice` in `PauseChoice::ALL` marked.
+fn pause_menu_text(bindings: &KeyBindings, choice: usize) -> String {
+    let mut text = String::from("Paused\n\n");
+    for (i, entry) in PauseChoice::ALL.iter().enumerate() {
+        let marker = if i == choice { '>' } else { ' ' };
+        text += &format!("{} {}\n", marker, entry.label());
+    }
+    text += &format!(
+        "\nUp/Down and Enter to choose, {} to resume or {} for help",
+        first_key(bindings, Action::Pause),
+        first_key(bindings, Action::Help),
+    );
+    text
+}
+
+/// The first key bound to `action`, quoted unless it is a named key like Space.
+fn first_key(bindings: &KeyBindings, action: Action) -> String {
+    match bindings.keys(action).first() {
+        Some(&KeyCode::Char(c)) if c != ' ' => format!("'{}'", c),
+        Some(&key) => key_name(key).to_uppercase(),
+        None => "(unbound)".to_string(),
+    }
+}
+
+/// The character set the rain is in, or the mix of them.
+fn language_text(config: &Config, language_keys: &[String]) -> String {
+    match config.mix.len() {
+        0 => language_keys[config.language_index].clone(),
+        1..=3 => format!("mix of {}", config.mix.join(", ")),
+        n => format!("mix of {} sets", n),
+    }
+}
+
+fn window_title(config: &Config, themes: &[ColorScheme], language_keys: &[String]) -> String {
+    format!("rusty_matrix \u{2014} {}, {}", themes[config.theme_index].name, language_text(config, language_keys))
+}
+
+fn config_menu_text(config: &Config, themes: &[ColorScheme], language_keys: &[String]) -> String {
+    let theme_name = themes[config.theme_index].name;
+    let current_language_name = language_text(config, language_keys);
+
+    let speed_text = match config.fps {
+        Some(fps) => format!("{} fps", fps),
+        None => format!("{} ({:.0} fps)", config.speed_level, config.frames_per_second()),
+    };
+    let max_len_text = match config.max_len {
+        Some(max_len) => max_len.to_string(),
+        None => "auto".to_string(),
+    };
+    let max_active_text = match config.max_active {
+        Some(max_active) => max_active.to_string(),
+        None => "all".to_string(),
+    };
+
+    format!(
+        "Configuration Menu\n\nSpeed: {} (use +/- to change)\nTheme: {} (use left/right arrows to change)\nLanguage: {} (use up/down arrows to change)\nMin length: {} (use m/M to change)\nMax length: {} (use x/X to change)\nDensity: {:.1} (use d/D to change)\nRaining columns: {} (use a/A to change)\nDirection: {} (use r to change)\n\nPress 'e': Edit the theme's colors\nPress 'c' or 'Esc': Return to matrix",
+        speed_text,
+        theme_name,
+        current_language_name,
+        config.min_len,
+        max_len_text,
+        config.density,
+        max_active_text,
+        config.direction.name()
+    )
+}
+
+fn clock_text(with_date: bool) -> String {
+    let format = if with_date { "%Y-%m-%d %H:%M:%S" } else { "%H:%M:%S" };
+    chrono::Local::now().format(format).to_string()
+}
+
+fn draw_ui(text: &str, out: &mut impl Write, clear_screen: bool, color_mode: ColorMode) -> std::io::Result<()> {
+    // Reset first so a cleared screen doesn't pick up the rain's background.
+    queue!(out, SetAttribute(Attribute::Reset))?;
     if clear_screen {
-        execute!(stdout, Clear(ClearType::All))?;
+        queue!(out, Clear(ClearType::All))?;
+    }
+    queue!(out, SetColor::foreground(color_mode, Color::White))?;
+    // Raw mode turns off the newline-to-CRLF translation, so place each line.
+    for (row, line) in text.lines().enumerate() {
+        queue!(out, cursor::MoveTo(0, row as u16), Print(line))?;
+    }
+    out.flush()
+}
+
+// --- Terminal Setup ---
+fn restore_terminal() -> std::io::Result<()> {
+    terminal::disable_raw_mode()?;
+    if TITLE_PUSHED.swap(false, Ordering::Relaxed) {
+        execute!(stdout(), Print(TITLE_POP))?;
+    }
+    #[cfg(feature = "backdrop")]
+    if KITTY_BACKDROP.swap(false, Ordering::Relaxed) {
+        execute!(stdout(), Print(backdrop::KITTY_DELETE))?;
+    }
+    if MAIN_SCREEN.load(Ordering::Relaxed) {
+        // Put the cursor under the rain, so the prompt comes back below it.
+        let (_, height) = terminal::size()?;
+        execute!(stdout(), DisableMouseCapture, ResetColor, cursor::Show, cursor::MoveTo(0, height.saturating_sub(1)))?;
+        return execute!(stdout(), Print("\r\n"));
+    }
+    execute!(stdout(), DisableMouseCapture, ResetColor, cursor::Show, LeaveAlternateScreen)
+}
+
+/// Leave raw mode and the alternate screen before the panic message is printed,
+/// then hand over to the previous hook so the message and backtrace still appear.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+}
+
+/// Loads the `--backdrop` picture to cover a `cols` x `rows` terminal, warning
+/// and carrying on without it when the terminal has no graphics or the picture
+/// can't be read.
+#[cfg(feature = "backdrop")]
+fn open_backdrop(cli: &Cli, cols: u16, rows: u16) -> Option<Backdrop> {
+    let path = cli.backdrop.as_ref()?;
+    let Some(protocol) = cli.backdrop_protocol.or_else(Protocol::detect) else {
+        eprintln!("warning: ignoring backdrop: this terminal doesn't seem to have kitty or sixel graphics (pick one with --backdrop-protocol)");
+        return None;
+    };
+    let pixels = terminal::window_size().ok().filter(|size| size.width > 0 && size.height > 0);
+    match Backdrop::load(path, protocol, cols, rows, pixels.map(|size| (size.width as u32, size.height as u32))) {
+        Ok(backdrop) => Some(backdrop),
+        Err(e) => {
+            eprintln!("warning: ignoring backdrop {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Starts listening for `--audio`, warning and carrying on without it when
+/// that isn't possible.
+#[cfg(feature = "audio")]
+fn open_audio(config: &Config) -> Option<AudioReactor> {
+    if !config.audio {
+        return None;
+    }
+    if config.cpu_monitor {
+        eprintln!("warning: ignoring audio: cpu_monitor also sets the speed");
+        return None;
+    }
+    let audio = AudioReactor::open();
+    if audio.is_none() {
+        eprintln!("warning: ignoring audio: no audio input is available");
+    }
+    audio
+}
+
+/// Starts the `--wave`, warning and leaving it off when something else already
+/// drives the speed or spawn rate.
+fn start_wave(config: &Config, now: Instant) -> Option<Wave> {
+    if !config.wave {
+        return None;
+    }
+    if config.cpu_monitor || config.audio {
+        let other = if config.cpu_monitor { "cpu_monitor" } else { "audio" };
+        eprintln!("warning: ignoring wave: {} also sets the speed", other);
+        return None;
+    }
+    Some(Wave::new(now))
+}
+
+/// Waits for the next input event, giving up with `None` once `quit` is set.
+fn next_event(quit: &AtomicBool) -> std::io::Result<Option<Event>> {
+    while !quit.load(Ordering::Relaxed) {
+        if event::poll(INPUT_POLL_INTERVAL)? {
+            return Ok(Some(event::read()?));
+        }
     }
-    execute!(
-        stdout,
-        cursor::MoveTo(0, 0),
-        SetForegroundColor(Color::White),
-        Print(text)
-    )?;
-    stdout.flush()
+    Ok(None)
 }
 
 // --- Main Application ---
 fn main() -> std::io::Result<()> {
-    let mut stdout = stdout();
-    let (width, height) = terminal::size()?;
+    install_panic_hook();
 
-    execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
-    terminal::enable_raw_mode()?;
+    let cli = parse_cli(&language_keys());
+    if let Some(path) = &cli.charset_file {
+        load_charset_file(path);
+    }
+    let art = cli.art_file.as_ref().map(|path| {
+        std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("error: could not read {}: {}", path.display(), e);
+            std::process::exit(2);
+        })
+    });
+    let language_keys = language_keys();
+    let config_path = config_path(&cli);
+    let file = ConfigFile::read(config_path.as_deref()).unwrap_or_else(|e| {
+        eprintln!("warning: {}", e);
+        ConfigFile::default()
+    });
+    let mut themes = build_themes(&cli, &file);
+    let (mut config, warnings) = load_config(&file, &themes, &language_keys);
+    let (bindings, key_warnings) = load_key_bindings(&file);
+    for warning in warnings.iter().chain(&key_warnings) {
+        eprintln!("warning: {}", warning);
+    }
+    apply_cli(&mut config, &cli, &themes, &language_keys);
+    let initial_config = config.clone();
+    if cli.print_config {
+        let json = serde_json::to_string_pretty(&config_json(&config, &themes, &language_keys)).map_err(std::io::Error::other)?;
+        return writeln!(stdout(), "{}", json);
+    }
+    if cli.dump.is_some() {
+        return dump_frames(&cli, &config, &themes, art.as_deref(), &mut stdout().lock());
+    }
+    // Raw mode and the alternate screen need a terminal; into a file or a pipe
+    // the escapes would only be garbage.
+    if !stdout().is_terminal() {
+        eprintln!("error: stdout is not a terminal; use --dump to print frames as text instead");
+        std::process::exit(1);
+    }
+    // SIGINT and SIGTERM only raise this flag, so the loop exits through the
+    // normal cleanup and the terminal is restored. They're registered only
+    // now, so `--dump`, `--print-config` and a `--config -` still waiting on
+    // stdin are killed by them as usual.
+    let quit = Arc::new(AtomicBool::new(false));
+    for signal in [signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&quit))?;
+    }
+    // Running out of time is handled like a signal, so it ends every state.
+    if let Some(duration) = cli.duration.filter(|d| !d.is_zero()) {
+        let quit = Arc::clone(&quit);
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            quit.store(true, Ordering::Relaxed);
+        });
+    }
+    #[cfg(feature = "audio")]
+    let mut audio = open_audio(&config);
+    #[cfg(not(feature = "audio"))]
+    if config.audio {
+        eprintln!("warning: ignoring audio: this build doesn't include the audio feature");
+    }
+
+    // Every frame is queued into this buffer and written out with a single flush.
+    let color_mode = config.color_mode.unwrap_or_else(ColorMode::detect);
+    let mut renderer = TerminalRenderer::new(Recorder::new(BufWriter::with_capacity(1 << 16, stdout())), color_mode);
+    // Light themes bring their own background, as the terminal's is likely dark.
+    let mut background = config.background.or(themes[config.theme_index].background);
+    renderer.set_background(background);
+    // Pipes and some CI runners have no terminal, or one that reports no size.
+    let (terminal_width, terminal_height) = match terminal::size() {
+        Ok((width, height)) if width > 0 && height > 0 => (width, height),
+        Ok((width, height)) => {
+            eprintln!("error: the terminal is {}x{}; rusty_matrix needs at least one row and column", width, height);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("error: could not get the terminal size: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // --size lays the rain out at its own size; whatever falls outside the
+    // terminal just isn't drawn.
+    let (width, height) = cli.size.unwrap_or((terminal_width, terminal_height));
+    check_size(&cli, width, height);
+    #[cfg(feature = "backdrop")]
+    let mut backdrop = open_backdrop(&cli, terminal_width, terminal_height);
+    #[cfg(not(feature = "backdrop"))]
+    if cli.backdrop.is_some() {
+        eprintln!("warning: ignoring backdrop: this build doesn't include the backdrop feature");
+    }
+    renderer.set_visible(terminal_width, terminal_height);
+    if let Some(path) = &cli.record
+        && let Err(e) = renderer.writer().record(path, terminal_width, terminal_height)
+    {
+        eprintln!("error: could not record to {}: {}", path.display(), e);
+        std::process::exit(2);
+    }
+
+    if cli.no_altscreen {
+        // Clearing the screen would lose what was on it, so scroll it away instead.
+        execute!(renderer.writer(), cursor::MoveTo(0, terminal_height.saturating_sub(1)))?;
+        execute!(renderer.writer(), Print("\n".repeat(terminal_height as usize)), cursor::Hide)?;
+        MAIN_SCREEN.store(true, Ordering::Relaxed);
+    } else {
+        execute!(renderer.writer(), EnterAlternateScreen, cursor::Hide)?;
+    }
+    if cli.mouse {
+        execute!(renderer.writer(), EnableMouseCapture)?;
+    }
+    if let Err(e) = terminal::enable_raw_mode() {
+        let _ = execute!(renderer.writer(), DisableMouseCapture, cursor::Show);
+        if !cli.no_altscreen {
+            let _ = execute!(renderer.writer(), LeaveAlternateScreen);
+        }
+        eprintln!("error: could not set up the terminal: {}", e);
+        std::process::exit(1);
+    }
+    if config.set_title {
+        execute!(renderer.writer(), Print(TITLE_PUSH))?;
+        TITLE_PUSHED.store(true, Ordering::Relaxed);
+    }
+    #[cfg(feature = "backdrop")]
+    if let Some(backdrop) = &backdrop {
+        execute!(renderer.writer(), Print(&backdrop.setup))?;
+        KITTY_BACKDROP.store(backdrop.protocol == Protocol::Kitty, Ordering::Relaxed);
+        renderer.set_backdrop(Some(backdrop.draw.clone()));
+    }
+    let mut title = String::new();
 
-    let mut columns: Vec<Column> = (0..width / 2).map(|x| Column::new(x, height)).collect();
     let mut app_state = AppState::Matrix;
-    let mut config = Config { theme_index: 0, speed_level: 5, language_index: 0 }; // Default to English
+    let mut editor: Option<ThemeEditor> = None;
+    let mut themes_edited = false;
+    let mut pause_choice = 0;
 
-    let language_keys: Vec<String> = ALL_CHAR_SETS.keys().cloned().collect();
+    // A single generator drives the whole simulation, so a fixed seed replays the
+    // exact same frames for the same settings and terminal size.
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut rain = Rain::new(width, height, layout_slot_width(&config, &language_keys), &config);
+    rain.truecolor = color_mode == ColorMode::TrueColor;
+    rain.dissolve = matches!(color_mode, ColorMode::TrueColor | ColorMode::Ansi256);
+    let mut frame = Frame::new(width, height);
+    let mut next_frame = Instant::now();
+    let mut next_draw = Instant::now();
+    let mut stats = FrameStats::new(Instant::now());
+    let started_at = Instant::now();
+    // Everything besides the rain itself falling. Its `frozen` holds the
+    // current frame still, unlike pausing, while keys and menus keep working.
+    let mut effects = Effects::new(&config, &themes[config.theme_index], started_at);
+    // The colors the last frame was drawn in.
+    let mut drawn_colors = themes[config.theme_index];
+    let mut clock_face = ClockFace::new();
+    let mut status: Option<(String, Instant)> = None;
+    let mut monitor = config.cpu_monitor.then(|| CpuMonitor::new(Instant::now()));
+    let wave = start_wave(&config, Instant::now());
+    // The speed, spawn chance and density those swing around; the keys change these.
+    let mut baseline = Baseline::new(&config);
+    if let Some(art) = &art {
+        let hold = Duration::from_secs_f64(cli.art_hold.unwrap_or(ART_HOLD_SECONDS));
+        let melt = Duration::from_secs_f64(cli.art_melt.unwrap_or(ART_MELT_SECONDS));
+        effects.reveal(art, hold, melt, &mut rng);
+    }
 
-    loop {
+    while !quit.load(Ordering::Relaxed) {
         match app_state {
-            AppState::Matrix => {
-                if event::poll(Duration::from_millis(SPEED_DURATIONS[config.speed_level - 1]))? {
-                    if let Event::Key(key) = event::read()? {
-                        match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => break,
-                            KeyCode::Char(' ') => app_state = AppState::Paused,
-                            KeyCode::Char('c') => app_state = AppState::Config,
+            // While paused the rain keeps falling, dimmed, behind the pause menu.
+            // It also keeps falling behind the config menu, so changes show at once,
+            // and behind the theme editor in the colors being edited.
+            AppState::Matrix | AppState::Paused | AppState::Config | AppState::ThemeEditor => {
+                let paused = matches!(app_state, AppState::Paused);
+                let in_menu = matches!(app_state, AppState::Config);
+                let timeout = next_frame.saturating_duration_since(Instant::now()).min(INPUT_POLL_INTERVAL);
+                if event::poll(timeout)? {
+                    match event::read()? {
+                        Event::Key(_) | Event::Mouse(_) if cli.screensaver => break,
+                        Event::Key(key) if let Some(edit) = &mut editor => match key.code {
+                            KeyCode::Up => edit.color = cycle(edit.color, EDITOR_COLORS.len(), false),
+                            KeyCode::Down => edit.color = cycle(edit.color, EDITOR_COLORS.len(), true),
+                            KeyCode::Left => edit.channel = cycle(edit.channel, EDITOR_CHANNELS.len(), false),
+                            KeyCode::Right => edit.channel = cycle(edit.channel, EDITOR_CHANNELS.len(), true),
+                            KeyCode::Char('+') | KeyCode::Char('=') => edit.adjust(true),
+                            KeyCode::Char('-') => edit.adjust(false),
+                            KeyCode::Enter => {
+                                let scheme = edit.scheme;
+                                config.theme_index = match themes.iter().position(|t| t.name == CUSTOM_THEME) {
+                                    Some(i) => {
+                                        themes[i] = scheme;
+                                        i
+                                    }
+                                    None => {
+                                        themes.push(scheme);
+                                        themes.len() - 1
+                                    }
+                                };
+                                themes_edited = true;
+                                editor = None;
+                                app_state = AppState::Config;
+                            }
+                            KeyCode::Esc => {
+                                editor = None;
+                                app_state = AppState::Config;
+                            }
                             _ => {},
+                        },
+                        Event::Key(key) if in_menu => match key.code {
+                            KeyCode::Char('c') | KeyCode::Esc => app_state = AppState::Matrix,
+                            KeyCode::Char('+') | KeyCode::Char('=') => baseline.step_speed(&mut config, true),
+                            KeyCode::Char('-') => baseline.step_speed(&mut config, false),
+                            KeyCode::Char('e') => {
+                                editor = Some(ThemeEditor::new(&themes[config.theme_index]));
+                                app_state = AppState::ThemeEditor;
+                            }
+                            KeyCode::Char('m') => {
+                                config.step_min_len(false, config.len_range(rain.lane_len()).1 as u16);
+                            }
+                            KeyCode::Char('M') => {
+                                config.step_min_len(true, config.len_range(rain.lane_len()).1 as u16);
+                            }
+                            KeyCode::Char('x') => {
+                                let max_len = config.len_range(rain.lane_len()).1 as u16;
+                                config.max_len = Some((max_len - 1).max(config.min_len));
+                            }
+                            KeyCode::Char('X') => {
+                                let max_len = config.len_range(rain.lane_len()).1 as u16;
+                                config.max_len = Some((max_len + 1).min(rain.lane_len().max(1)));
+                            }
+                            KeyCode::Char('d') | KeyCode::Char('D') => {
+                                let delta = if key.code == KeyCode::Char('d') { -0.1 } else { 0.1 };
+                                // Round to one decimal so repeated steps don't drift.
+                                let density = ((baseline.density + delta) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+                                baseline.set_density(&mut config, density);
+                                relayout(&mut rain, frame.width(), frame.height(), &config, &language_keys);
+                            }
+                            // Past the number of columns, the cap lifts altogether.
+                            KeyCode::Char('a') => {
+                                let max_active = config.max_active.unwrap_or(rain.columns.len());
+                                config.max_active = Some(max_active.saturating_sub(1).max(1));
+                            }
+                            KeyCode::Char('A') => {
+                                config.max_active = config.max_active.map(|n| n + 1).filter(|&n| n < rain.columns.len());
+                            }
+                            KeyCode::Char('r') => {
+                                config.direction = config.direction.next();
+                                relayout(&mut rain, frame.width(), frame.height(), &config, &language_keys);
+                            }
+                            KeyCode::Right | KeyCode::Left => {
+                                let forward = key.code == KeyCode::Right;
+                                config.theme_index = cycle(config.theme_index, themes.len(), forward);
+                            }
+                            KeyCode::Up | KeyCode::Down => {
+                                let forward = key.code == KeyCode::Up;
+                                config.language_index = cycle(config.language_index, language_keys.len(), forward);
+                                relayout(&mut rain, frame.width(), frame.height(), &config, &language_keys);
+                            }
+                            _ => {},
+                        },
+                        // The menu's own keys come first, then the usual bindings.
+                        Event::Key(key) if paused => match (key.code, bindings.action(key.code)) {
+                            (KeyCode::Up, _) => pause_choice = cycle(pause_choice, PauseChoice::ALL.len(), false),
+                            (KeyCode::Down, _) => pause_choice = cycle(pause_choice, PauseChoice::ALL.len(), true),
+                            (KeyCode::Enter, _) => match PauseChoice::ALL[pause_choice] {
+                                PauseChoice::Resume => app_state = AppState::Matrix,
+                                PauseChoice::Config => app_state = AppState::Config,
+                                PauseChoice::Quit => break,
+                            },
+                            (_, Some(Action::Quit)) => break,
+                            (_, Some(Action::Pause)) => app_state = AppState::Matrix,
+                            (_, Some(Action::Help)) => app_state = AppState::Help,
+                            _ => {},
+                        },
+                        Event::Key(key) => match bindings.action(key.code) {
+                            Some(Action::Quit) => break,
+                            Some(Action::Pause) => {
+                                pause_choice = 0;
+                                app_state = AppState::Paused;
+                            }
+                            Some(Action::Freeze) => {
+                                effects.frozen = !effects.frozen;
+                                let text = if effects.frozen {
+                                    format!("Frozen, press {} to resume", first_key(&bindings, Action::Freeze))
+                                } else {
+                                    "Resumed".to_string()
+                                };
+                                status = Some((text, Instant::now() + STATUS_DURATION));
+                            }
+                            Some(Action::Config) => app_state = AppState::Config,
+                            Some(Action::Help) => app_state = AppState::Help,
+                            Some(Action::Screenshot) => {
+                                let text = match save_screenshot(&frame, config.screenshot_format, color_mode) {
+                                    Ok(name) => format!("Saved {}", name),
+                                    Err(e) => format!("Could not save screenshot: {}", e),
+                                };
+                                status = Some((text, Instant::now() + STATUS_DURATION));
+                            }
+                            Some(action @ (Action::ThemeNext | Action::ThemePrev)) => {
+                                let forward = action == Action::ThemeNext;
+                                config.theme_index = cycle(config.theme_index, themes.len(), forward);
+                            }
+                            Some(action @ (Action::LanguageNext | Action::LanguagePrev)) => {
+                                let forward = action == Action::LanguageNext;
+                                config.language_index = cycle(config.language_index, language_keys.len(), forward);
+                                relayout(&mut rain, frame.width(), frame.height(), &config, &language_keys);
+                            }
+                            Some(action @ (Action::Faster | Action::Slower)) => {
+                                baseline.step_speed(&mut config, action == Action::Faster)
+                            }
+                            None => {},
+                        },
+                        Event::Mouse(mouse) if !paused => {
+                            let key = &language_keys[config.language_index];
+                            let slot = layout_slot_width(&config, &language_keys);
+                            // A click starts a small burst; dragging leaves a drop at each spot passed.
+                            let mut xs = match mouse.kind {
+                                MouseEventKind::Down(MouseButton::Left) => {
+                                    vec![mouse.column.saturating_sub(slot), mouse.column, mouse.column + slot]
+                                }
+                                MouseEventKind::Drag(MouseButton::Left) => vec![mouse.column],
+                                _ => Vec::new(),
+                            };
+                            xs.dedup();
+                            for x in xs {
+                                let char = get_random_char(key, &mut rng);
+                                rain.seed(x, mouse.row, char, &config, &mut rng);
+                            }
                         }
+                        Event::Resize(w, h) => {
+                            renderer.writer().resize(w, h)?;
+                            renderer.set_visible(w, h);
+                            #[cfg(feature = "backdrop")]
+                            if let Some(backdrop) = &mut backdrop {
+                                backdrop.resize(w, h);
+                                renderer.set_backdrop(Some(backdrop.draw.clone()));
+                            }
+                            // Growing past the cap keeps the old layout, like --size does.
+                            if cli.size.is_none() && (cli.force || !too_many_cells(w, h)) {
+                                relayout(&mut rain, w, h, &config, &language_keys);
+                                frame.resize(w, h);
+                            }
+                        }
+                        _ => {},
+                    }
+                }
+
+                // Input is handled as soon as it arrives; the rain only advances once
+                // the per-speed interval has elapsed since the previous frame.
+                let now = Instant::now();
+                if now < next_frame {
+                    continue;
+                }
+                let interval = config.frame_interval();
+                next_frame += interval;
+                if next_frame < now {
+                    // Fell behind (or just came back from a menu): resume from now
+                    // rather than rushing through the missed frames.
+                    next_frame = now + interval;
+                }
+
+                if let Some(monitor) = &mut monitor
+                    && monitor.poll(now)
+                {
+                    let density = config.density;
+                    monitor.apply(&baseline, &mut config);
+                    if config.density != density {
+                        relayout(&mut rain, frame.width(), frame.height(), &config, &language_keys);
                     }
                 }
 
-                execute!(stdout, Clear(ClearType::All))?;
-                let colors = &THEMES[config.theme_index];
+                #[cfg(feature = "audio")]
+                if let Some(audio) = &mut audio {
+                    audio.apply(&baseline, &mut config);
+                }
+                if let Some(wave) = &wave {
+                    wave.apply(&baseline, &mut config, now);
+                }
+
+                let started = config.show_fps.then(Instant::now);
+                let target = editor.as_ref().map_or(&themes[config.theme_index], |edit| &edit.scheme);
+                let colors = &effects.colors(&config, target, color_mode == ColorMode::TrueColor, now);
+                drawn_colors = *colors;
+                if config.background.or(colors.background) != background {
+                    background = config.background.or(colors.background);
+                    renderer.set_background(background);
+                }
                 let current_language_key = &language_keys[config.language_index];
-                for col in columns.iter_mut() {
-                    col.update(colors, current_language_key);
-                    col.draw(&mut stdout);
+                rain.brightness = if paused { PAUSE_BRIGHTNESS } else { 1.0 };
+                effects.advance(&mut rain, &config, colors, current_language_key, now, &mut rng);
+                // Under --max-fps the rain can step several times between draws.
+                if let Some(interval) = config.draw_interval() {
+                    if now < next_draw {
+                        if let Some(started) = started {
+                            let now = Instant::now();
+                            stats.record(now - started, false, now);
+                        }
+                        continue;
+                    }
+                    next_draw += interval;
+                    if next_draw < now {
+                        next_draw = now + interval;
+                    }
                 }
-                stdout.flush()?;
-            }
-            AppState::Paused => {
-                // Do not clear screen, just overlay message
-                draw_ui("Paused - Press SPACE to resume or 'q' to quit", &mut stdout, false)?;
-                if let Event::Key(key) = event::read()? {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => break,
-                        KeyCode::Char(' ') => app_state = AppState::Matrix,
-                        _ => {},
+                frame.clear();
+                let shift = if config.shift_overlays { overlay::shift(now - started_at) } else { (0, 0) };
+                if config.big_clock {
+                    let format = if config.big_clock_seconds { "%H:%M:%S" } else { "%H:%M" };
+                    let text = chrono::Local::now().format(format).to_string();
+                    clock_face.draw(&mut frame, &text, &config, colors, &language_keys, &mut rng);
+                }
+                if config.clock {
+                    overlay::draw_shifted(&mut frame, config.clock_corner, shift, &[clock_text(config.clock_date)], colors.ink());
+                }
+                if config.show_fps {
+                    let corner = if config.clock && config.clock_corner == Corner::TopLeft {
+                        Corner::TopRight
+                    } else {
+                        Corner::TopLeft
+                    };
+                    overlay::draw_shifted(&mut frame, corner, shift, &[stats.text().to_string()], colors.ink());
+                }
+                if config.show_cpu
+                    && let Some(monitor) = &monitor
+                {
+                    overlay::draw_shifted(&mut frame, Corner::BottomRight, shift, &[monitor.text()], colors.ink());
+                }
+                status = status.filter(|(_, until)| now < *until);
+                if let Some((text, _)) = &status {
+                    overlay::draw_shifted(&mut frame, Corner::BottomLeft, shift, std::slice::from_ref(text), colors.ink());
+                }
+                let menu_text = match &editor {
+                    Some(edit) => Some(edit.text()),
+                    None if in_menu => Some(config_menu_text(&config, &themes, &language_keys)),
+                    None if paused => Some(pause_menu_text(&bindings, pause_choice)),
+                    None => None,
+                };
+                if let Some(text) = menu_text {
+                    let lines: Vec<String> = text.lines().map(String::from).collect();
+                    overlay::draw_shifted(&mut frame, Corner::TopLeft, shift, &lines, colors.ink());
+                }
+                effects.draw(&rain, &config, &mut frame, colors, current_language_key, &mut rng);
+                if config.set_title {
+                    let text = window_title(&config, &themes, &language_keys);
+                    if text != title {
+                        queue!(renderer.writer(), SetTitle(&text))?;
+                        title = text;
                     }
                 }
+                renderer.render(&frame)?;
+                if let Some(started) = started {
+                    let now = Instant::now();
+                    stats.record(now - started, true, now);
+                }
             }
-            AppState::Config => {
-                let theme_name = match config.theme_index {
-                    0 => "Classic Green",
-                    1 => "Ocean Blue",
-                    2 => "Crimson Red",
-                    3 => "Cyberpunk",
-                    _ => "Unknown",
-                };
-                let current_language_name = &language_keys[config.language_index];
-
-                let menu_text = format!(
-                    "Configuration Menu\n\nSpeed: {} (use +/- to change)\nTheme: {} (use left/right arrows to change)\nLanguage: {} (use up/down arrows to change)\n\nPress 'c' or 'Esc': Return to matrix",
-                    config.speed_level,
-                    theme_name,
-                    current_language_name
-                );
-                draw_ui(&menu_text, &mut stdout, true)?;
-
-                if let Event::Key(key) = event::read()? {
-                    match key.code {
-                        KeyCode::Char('c') | KeyCode::Esc => app_state = AppState::Matrix,
-                        KeyCode::Char('+') | KeyCode::Char('=') => {
-                            config.speed_level = (config.speed_level + 1).min(10);
-                        }
-                        KeyCode::Char('-') => {
-                            config.speed_level = (config.speed_level - 1).max(1);
-                        }
-                        KeyCode::Right => {
-                            config.theme_index = (config.theme_index + 1) % THEMES.len();
-                        }
-                        KeyCode::Left => {
-                            config.theme_index = if config.theme_index == 0 {
-                                THEMES.len() - 1
-                            } else {
-                                config.theme_index - 1
-                            };
-                        }
-                        KeyCode::Up => {
-                            config.language_index = (config.language_index + 1) % language_keys.len();
+            AppState::Help => {
+                draw_ui(&help_text(&bindings), renderer.writer(), true, color_mode)?;
+                match next_event(&quit)? {
+                    Some(Event::Key(_)) => {
+                        app_state = AppState::Matrix;
+                        renderer.invalidate();
+                    }
+                    Some(Event::Resize(w, h)) => {
+                        renderer.writer().resize(w, h)?;
+                        renderer.set_visible(w, h);
+                        #[cfg(feature = "backdrop")]
+                        if let Some(backdrop) = &mut backdrop {
+                            backdrop.resize(w, h);
+                            renderer.set_backdrop(Some(backdrop.draw.clone()));
                         }
-                        KeyCode::Down => {
-                            config.language_index = if config.language_index == 0 {
-                                language_keys.len() - 1
-                            } else {
-                                config.language_index - 1
-                            };
+                        if cli.size.is_none() && (cli.force || !too_many_cells(w, h)) {
+                            relayout(&mut rain, w, h, &config, &language_keys);
+                            frame.resize(w, h);
                         }
-                        _ => {},
                     }
+                    _ => {},
                 }
             }
         }
     }
 
+    if config.fade_out {
+        let language_key = &language_keys[config.language_index];
+        simulation::fade_out(&mut renderer, &mut rain, &mut frame, &config, &drawn_colors, language_key, &mut rng)?;
+    }
+
     // Cleanup
-    execute!(stdout, cursor::Show, LeaveAlternateScreen)?;
-    terminal::disable_raw_mode()?;
+    renderer.writer().flush()?;
+    restore_terminal()?;
+
+    // What the drivers swung is saved as picked.
+    let driven = monitor.is_some() || wave.is_some();
+    #[cfg(feature = "audio")]
+    let driven = driven || audio.is_some();
+    if driven {
+        baseline.restore(&mut config);
+    }
+    if config.save_on_exit
+        && (config != initial_config || themes_edited)
+        && let Err(e) = save_config(config_path.as_deref(), &config, &themes, &language_keys)
+    {
+        eprintln!("warning: could not save config: {}", e);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The command line `cli` as `parse_cli` sees it with `env` set.
+    fn parse(env: &[(&str, &str)], cli: &[&str]) -> Result<Cli, clap::Error> {
+        let command = Cli::command().args_override_self(true);
+        let cli_args: Vec<String> = std::iter::once("rusty_matrix").chain(cli.iter().copied()).map(String::from).collect();
+        let var = |name: &str| env.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string());
+        let env_args = env_args(&command, &cli_args, var, &language_keys());
+        let matches = command.try_get_matches_from(cli_args[..1].iter().chain(&env_args).chain(&cli_args[1..]))?;
+        Cli::from_arg_matches(&matches)
+    }
+
+    #[test]
+    fn custom_theme_comes_from_the_environment() {
+        let env = [("RUSTY_MATRIX_HEAD", "#ff0000"), ("RUSTY_MATRIX_TRAIL", "#00ff00"), ("RUSTY_MATRIX_FADE", "#0000ff")];
+        let cli = parse(&env, &[]).unwrap();
+        assert_eq!(cli.head, Some(Color::Rgb { r: 255, g: 0, b: 0 }));
+        assert_eq!(cli.trail, Some(Color::Rgb { r: 0, g: 255, b: 0 }));
+        assert_eq!(cli.fade, Some(Color::Rgb { r: 0, g: 0, b: 255 }));
+    }
+
+    #[test]
+    fn command_line_beats_a_conflicting_environment() {
+        let cli = parse(&[("RUSTY_MATRIX_THEME", "Cyberpunk")], &["--light"]).unwrap();
+        assert!(cli.light);
+        assert_eq!(cli.theme, None);
+
+        let cli = parse(&[("RUSTY_MATRIX_THEME", "Cyberpunk")], &["--theme", "Classic"]).unwrap();
+        assert_eq!(cli.theme.as_deref(), Some("Classic"));
+    }
+
+    #[test]
+    fn seeded_dump_comes_out_the_same() {
+        let args = ["--dump", "--frames", "3", "--size", "12x4", "--seed", "42", "--lang", "Binary", "--color-mode", "16"];
+        let effects = ["--spawn-chance", "1", "--start-offset-max", "0", "--glitch"];
+        let cli = parse(&[], &[&args[..], &effects].concat()).unwrap();
+        let file = ConfigFile::default();
+        let themes = build_themes(&cli, &file);
+        let (mut config, _) = load_config(&file, &themes, &language_keys());
+        apply_cli(&mut config, &cli, &themes, &language_keys());
+        let mut out = Vec::new();
+        dump_frames(&cli, &config, &themes, Some("HI"), &mut out).unwrap();
+        let expected = concat!(
+            "--- frame 1 ---\n",
+            "          0\n",
+            "     HI\n",
+            "\n",
+            "\n",
+            "--- frame 2 ---\n",
+            "  1       0\n",
+            "     HI   0\n",
+            "\n",
+            "\n",
+            "--- frame 3 ---\n",
+            "  1 1   1\n",
+            "     HI   0\n",
+            "          1\n",
+            "\n",
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn options_that_need_others_come_from_the_environment() {
+        let cli = parse(&[("RUSTY_MATRIX_DUMP", "1"), ("RUSTY_MATRIX_FRAMES", "3")], &[]).unwrap();
+        assert_eq!(cli.dump, Some(ScreenshotFormat::Text));
+        assert_eq!(cli.frames, Some(3));
+    }
+}
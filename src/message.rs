@@ -0,0 +1,67 @@
+use crate::config::Config;
+use crate::rain::Rain;
+use rand::Rng;
+
+/// Text that now and then gets spelled out by the heads of a band of adjacent
+/// columns, then falls and fades with the rest of the rain.
+pub struct Message {
+    text: String,
+    next_line: usize,
+}
+
+impl Message {
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), next_line: 0 }
+    }
+
+    /// Spells the next line of the message across the rain, centered. A message
+    /// wider than the rain is word-wrapped, and each call shows the following line.
+    pub fn show(&mut self, rain: &mut Rain, config: &Config, rng: &mut impl Rng) {
+        let lines = wrap(&self.text, rain.columns.len());
+        let lane_len = rain.lane_len() as i16;
+        if lines.is_empty() || lane_len == 0 {
+            return;
+        }
+        let line = &lines[self.next_line % lines.len()];
+        self.next_line = (self.next_line + 1) % lines.len();
+
+        // Upper half, so the letters stay on screen for a while as they fall.
+        let row = rng.gen_range(0..=lane_len / 2).min(lane_len - 1);
+        let len = rng.gen_range(lane_len / 4..=lane_len / 2).max(1);
+        let velocity = config.random_velocity(rng);
+        let start = (rain.columns.len() - line.len()) / 2;
+        for (col, &c) in rain.columns[start..].iter_mut().zip(line) {
+            if !c.is_whitespace() {
+                col.spell(row, c, len, velocity);
+            }
+        }
+    }
+}
+
+/// Breaks `text` into lines of at most `width` characters, at spaces where it
+/// can and mid-word where a word alone is too long.
+fn wrap(text: &str, width: usize) -> Vec<Vec<char>> {
+    let mut lines: Vec<Vec<char>> = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+    let mut line = Vec::new();
+    for word in text.split_whitespace() {
+        let word: Vec<char> = word.chars().collect();
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+        while line.len() > width {
+            let rest = line.split_off(width);
+            lines.push(std::mem::replace(&mut line, rest));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
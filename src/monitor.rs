@@ -0,0 +1,58 @@
+use crate::baseline::Baseline;
+use crate::config::{Config, SPEED_DURATIONS};
+use std::time::{Duration, Instant};
+use sysinfo::System;
+
+/// How often CPU usage is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Samples overall CPU usage once a second and turns it into rain intensity:
+/// an idle machine gets the picked speed and density, a fully
+/// busy one `cpu_speed_level` and `cpu_density`, and anything between a blend.
+pub struct CpuMonitor {
+    system: System,
+    next_sample: Instant,
+    /// Percentage of the last sample, 0-100.
+    usage: f32,
+}
+
+impl CpuMonitor {
+    pub fn new(now: Instant) -> Self {
+        // Usage is measured between two refreshes, so the first one only sets a baseline.
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+        Self {
+            system,
+            next_sample: now + SAMPLE_INTERVAL,
+            usage: 0.0,
+        }
+    }
+
+    /// Takes a new sample if one is due, returning whether it did.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if now < self.next_sample {
+            return false;
+        }
+        self.system.refresh_cpu_usage();
+        self.usage = self.system.global_cpu_usage().clamp(0.0, 100.0);
+        self.next_sample = now + SAMPLE_INTERVAL;
+        true
+    }
+
+    /// Sets `config`'s speed and density for the last sample, from `baseline` at
+    /// no load, dropping any `fps` override. Density moves in the same tenths
+    /// as the density keys, so small swings don't re-lay out the rain every second.
+    pub fn apply(&self, baseline: &Baseline, config: &mut Config) {
+        let load = self.usage / 100.0;
+        let idle_speed = baseline.speed_level as f32;
+        let speed = idle_speed + (config.cpu_speed_level as f32 - idle_speed) * load;
+        config.fps = None;
+        config.speed_level = (speed.round() as usize).clamp(1, SPEED_DURATIONS.len());
+        let density = baseline.density + (config.cpu_density - baseline.density) * load;
+        config.density = ((density * 10.0).round() / 10.0).clamp(0.0, 1.0);
+    }
+
+    pub fn text(&self) -> String {
+        format!("CPU {:.0}%", self.usage)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::color::Color;
+use crate::frame::Frame;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+/// How long shifted overlays stay in one place.
+pub const SHIFT_INTERVAL: Duration = Duration::from_secs(180);
+
+/// Offsets from the corner that shifted overlays step through, one per
+/// interval, walking a small loop so no cell stays lit for long.
+const SHIFT_PATH: [(u16, u16); 6] = [(0, 0), (1, 0), (2, 0), (2, 1), (1, 1), (0, 1)];
+
+/// Where shifted overlays sit `elapsed` after starting, in cells toward the
+/// middle of the screen from their corner.
+pub fn shift(elapsed: Duration) -> (u16, u16) {
+    let step = elapsed.as_secs() / SHIFT_INTERVAL.as_secs();
+    SHIFT_PATH[(step % SHIFT_PATH.len() as u64) as usize]
+}
+
+/// Corner of the screen an overlay is pinned to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [Corner::TopLeft, Corner::TopRight, Corner::BottomLeft, Corner::BottomRight];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "top-left",
+            Corner::TopRight => "top-right",
+            Corner::BottomLeft => "bottom-left",
+            Corner::BottomRight => "bottom-right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Prints `lines` in `corner` with a blank cell of padding on either side, and
+/// reserves that box so rain drawn afterwards goes around it.
+pub fn draw(frame: &mut Frame, corner: Corner, lines: &[String], color: Color) {
+    draw_shifted(frame, corner, (0, 0), lines, color);
+}
+
+/// Like [`draw`], but moved `shift` cells in from the corner, as given by [`shift`].
+pub fn draw_shifted(frame: &mut Frame, corner: Corner, shift: (u16, u16), lines: &[String], color: Color) {
+    let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16 + 2;
+    let height = lines.len() as u16;
+    let (dx, dy) = shift;
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => dx.min(frame.width().saturating_sub(width)),
+        Corner::TopRight | Corner::BottomRight => frame.width().saturating_sub(width + dx),
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => dy.min(frame.height().saturating_sub(height)),
+        Corner::BottomLeft | Corner::BottomRight => frame.height().saturating_sub(height + dy),
+    };
+    frame.reserve(x, y, width, height);
+    for (row, line) in lines.iter().enumerate() {
+        frame.print(x + 1, y + row as u16, line, color);
+    }
+}
@@ -0,0 +1,1127 @@
+use crate::charset::{get_random_char, mirrored};
+use crate::color::{lerp, Color};
+use crate::config::Config;
+use crate::frame::{Frame, Glyph, BLANK};
+use crate::theme::{rainbow, ColorScheme, RAINBOW_THEME};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+#[derive(Clone)]
+pub struct Cell {
+    pub char: char,
+    pub color: Color,
+    pub lifetime: i16,
+    /// Flashes bright for the frame it was spawned in.
+    pub glint: bool,
+    /// Part of a message, so it keeps its character instead of mutating.
+    pub fixed: bool,
+    /// Frames since the cell was spawned, for fading in new heads.
+    pub age: u16,
+    /// Frames left that an expired cell lingers as a dim ghost of its last
+    /// character under `ghosts`; 0 once it is empty.
+    pub ghost: u8,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self { char: ' ', color: Color::Black, lifetime: 0, glint: false, fixed: false, age: 0, ghost: 0 }
+    }
+}
+
+/// How trail cells are colored as they age.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Shading {
+    /// The last few cells behind the head use `trail`, the rest `fade`.
+    Stepped,
+    /// As `Stepped`, but `fade` darkens toward black as each cell's lifetime
+    /// runs out, so the tail dissolves into the background. Needs at least 256
+    /// colors for the in-between shades.
+    Dissolving,
+    /// Blend from `head` through `trail` to `fade` along the drop, quantized to
+    /// `steps` shades when set. Needs a truecolor terminal to look right.
+    Gradient { steps: Option<usize> },
+}
+
+/// Which way the drops travel across the screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::Down, Direction::Up, Direction::Left, Direction::Right];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Down => "down",
+            Direction::Up => "up",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The next direction in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&d| d == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Drops run along screen columns rather than rows.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Direction::Down | Direction::Up)
+    }
+}
+
+/// How a drop's speed sets its brightness, so the rain seems to fall in layers.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Depth {
+    /// Every drop at full brightness.
+    Off,
+    /// Fast drops bright and slow ones dim, as if the slow ones were further off.
+    FastBright,
+    /// Slow drops bright and fast ones dim.
+    FastDim,
+}
+
+impl Depth {
+    pub const ALL: [Depth; 3] = [Depth::Off, Depth::FastBright, Depth::FastDim];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Depth::Off => "off",
+            Depth::FastBright => "fast-bright",
+            Depth::FastDim => "fast-dim",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Brightness of the dimmest drops under `Depth`.
+const DEPTH_MIN_BRIGHTNESS: f32 = 0.35;
+
+/// `colors` dimmed for a drop moving at `velocity`, by where that falls in the
+/// speed jitter range. Only gradients have the shades for it.
+fn depth_colors(config: &Config, colors: &ColorScheme, velocity: f32, shading: Shading) -> ColorScheme {
+    if config.depth == Depth::Off || !matches!(shading, Shading::Gradient { .. }) {
+        return *colors;
+    }
+    let fastest = 1.0 / config.speed_jitter_min.max(1) as f32;
+    let slowest = 1.0 / config.speed_jitter_max.max(config.speed_jitter_min).max(1) as f32;
+    let speed = if fastest > slowest { ((velocity - slowest) / (fastest - slowest)).clamp(0.0, 1.0) } else { 1.0 };
+    let t = if config.depth == Depth::FastBright { speed } else { 1.0 - speed };
+    colors.dimmed(DEPTH_MIN_BRIGHTNESS + (1.0 - DEPTH_MIN_BRIGHTNESS) * t)
+}
+
+/// Where `trail` sits along a gradient, as a fraction of the drop length.
+const GRADIENT_TRAIL_STOP: f32 = 0.25;
+
+fn gradient_color(colors: &ColorScheme, lifetime: i16, len: i16, steps: Option<usize>) -> Color {
+    let mut t = 1.0 - lifetime as f32 / len.max(1) as f32;
+    if let Some(steps) = steps {
+        let last = steps.max(2) as f32 - 1.0;
+        t = (t * last).round() / last;
+    }
+    if t < GRADIENT_TRAIL_STOP {
+        lerp(colors.head, colors.trail, t / GRADIENT_TRAIL_STOP)
+    } else {
+        lerp(colors.trail, colors.fade, (t - GRADIENT_TRAIL_STOP) / (1.0 - GRADIENT_TRAIL_STOP))
+    }
+}
+
+/// How much of the fade color a ghost keeps, where there are shades for it.
+const GHOST_BRIGHTNESS: f32 = 0.15;
+
+/// Color of a cell lingering as a ghost after its trail has gone. Stepped
+/// shading has no darker shade than fade, so ghosts are drawn dim instead.
+fn ghost_color(colors: &ColorScheme, shading: Shading) -> Color {
+    match shading {
+        Shading::Stepped => colors.fade,
+        Shading::Dissolving | Shading::Gradient { .. } => lerp(colors.base(), colors.fade, GHOST_BRIGHTNESS),
+    }
+}
+
+/// Color of a trail cell `remaining` cells from the end of a drop `len` long,
+/// whose first `bright` cells are drawn in the trail color when stepped.
+fn trail_color(colors: &ColorScheme, shading: Shading, remaining: i16, len: i16, bright: i16) -> Color {
+    match shading {
+        Shading::Stepped | Shading::Dissolving if remaining > len - bright => colors.trail,
+        Shading::Stepped => colors.fade,
+        Shading::Dissolving => lerp(colors.base(), colors.fade, remaining as f32 / len.max(1) as f32),
+        Shading::Gradient { steps } => gradient_color(colors, remaining, len, steps),
+    }
+}
+
+/// How far a bold drop's trail and fade colors move toward the next brighter
+/// ones along a gradient.
+const BOLD_DROP_BOOST: f32 = 0.3;
+
+/// Frames a lightning bolt takes to cross the screen.
+const LIGHTNING_FRAMES: u16 = 2;
+
+/// Progress short of a whole cell that still counts as a move, so that drops
+/// taking a third of a cell per frame don't lose a frame to rounding.
+const STEP_EPSILON: f32 = 1e-4;
+
+/// One drop: its head position, the length of the trail it leaves, and how
+/// fast it moves.
+pub struct Drop {
+    /// Cell the head is in.
+    pub head: i16,
+    pub len: i16,
+    /// Cells moved per frame, at most 1.0.
+    pub velocity: f32,
+    /// How far the head has moved toward the next cell, from 0.0 to 1.0.
+    pub offset: f32,
+    /// Character for the next head instead of a random one.
+    pub pending: Option<char>,
+    /// Character and color to show faintly in the cell the head moves into
+    /// next, with smooth motion.
+    pub ahead: Option<(char, Color)>,
+    /// Cells the head jumps each time it moves: 1, except for lightning.
+    pub stride: u16,
+    /// A lightning bolt, drawn bright from head to tail.
+    pub lightning: bool,
+    /// Cells from the head on drawn in the head color: the theme's
+    /// `head_length`, as long as the drop allows, taken as the drop moves.
+    pub hot: i16,
+    /// Drawn bold, and a little brighter where there are shades for it, from
+    /// head to tail; picked with `bold_drop_chance` when the drop starts.
+    pub bold: bool,
+}
+
+impl Drop {
+    fn new(lane_len: u16, config: &Config, rng: &mut impl Rng) -> Self {
+        let (min_len, max_len) = config.len_range(lane_len);
+        let above = rng.gen_range(config.start_offset_min..=config.start_offset_max.max(config.start_offset_min));
+        Self {
+            // Starting some way above the edge staggers when heads enter.
+            head: -1 - above.min(i16::MAX as u16 - 1) as i16,
+            len: rng.gen_range(min_len..=max_len),
+            velocity: config.random_velocity(rng),
+            offset: 0.0,
+            pending: None,
+            ahead: None,
+            stride: 1,
+            lightning: false,
+            hot: 1,
+            // Only drawn on when wanted, so seeded rain stays the same without it.
+            bold: config.bold_drop_chance > 0.0 && rng.gen_bool(config.bold_drop_chance),
+        }
+    }
+
+    /// A bolt as long as the lane that crosses it in `LIGHTNING_FRAMES`.
+    fn lightning(lane_len: u16) -> Self {
+        Self {
+            head: -1,
+            len: lane_len.max(1) as i16,
+            velocity: 1.0,
+            offset: 0.0,
+            pending: None,
+            ahead: None,
+            stride: lane_len.div_ceil(LIGHTNING_FRAMES).max(1),
+            lightning: true,
+            hot: 1,
+            bold: false,
+        }
+    }
+
+    /// `head_length` for the theme named `theme`, no longer than the drop.
+    fn head_cells(&self, config: &Config, theme: &str) -> i16 {
+        config.head_length_for(theme).min(self.len.max(1) as u16) as i16
+    }
+
+    /// How many cells from the head are drawn bright; all of them for lightning.
+    fn bright(&self, config: &Config) -> i16 {
+        if self.lightning { self.len } else { config.bright_trail.cells(self.len) }
+    }
+
+    /// The colors this drop is drawn in.
+    fn colors(&self, config: &Config, colors: &ColorScheme, shading: Shading) -> ColorScheme {
+        if self.lightning {
+            let flash = lerp(colors.head, colors.ink(), config.lightning_brightness);
+            ColorScheme { head: flash, trail: flash, fade: flash, ..*colors }
+        } else if self.bold && matches!(shading, Shading::Gradient { .. }) {
+            let colors = depth_colors(config, colors, self.velocity, shading);
+            let trail = lerp(colors.trail, colors.head, BOLD_DROP_BOOST);
+            let fade = lerp(colors.fade, colors.trail, BOLD_DROP_BOOST);
+            ColorScheme { trail, fade, ..colors }
+        } else {
+            depth_colors(config, colors, self.velocity, shading)
+        }
+    }
+
+    /// Trail left at cell `i`: the lifetime this drop gave it, or less than 1
+    /// once the head is too far ahead.
+    fn remaining(&self, i: usize) -> i16 {
+        self.len - (self.head - i as i16)
+    }
+
+    /// Indices of the cells this drop's trail covers, head included, clipped to
+    /// a column `cells` long.
+    fn trail(&self, cells: usize) -> std::ops::Range<usize> {
+        let start = (self.head - self.len + 1).max(0) as usize;
+        let end = (self.head + 1).clamp(0, cells as i16) as usize;
+        start.min(end)..end
+    }
+}
+
+/// A column of falling drops and the trails of cells they leave behind.
+/// `cells` runs in the direction of travel, so index 0 is where each drop
+/// enters the screen.
+pub struct Column {
+    /// Position across the direction of travel: a screen column for vertical
+    /// rain, a row for horizontal rain.
+    pub x: u16,
+    pub cells: Vec<Cell>,
+    /// Drops in the order they started, so the last one is nearest the entry.
+    pub drops: Vec<Drop>,
+    /// Frames left before another drop may start.
+    pub idle: u16,
+    /// Carries only the drops it is given and never starts its own.
+    pub transient: bool,
+    /// Kept from starting a drop this tick, to stay under `max_active`.
+    pub held: bool,
+    /// Index into `config.mix` of the set this column draws from, picked
+    /// afresh whenever it starts a drop with none in flight.
+    pub language: usize,
+}
+
+impl Column {
+    pub fn new(x: u16, height: u16) -> Self {
+        Self {
+            x,
+            cells: vec![Cell::default(); height as usize],
+            drops: Vec::new(),
+            idle: 0,
+            transient: false,
+            held: false,
+            language: 0,
+        }
+    }
+
+    /// The set this column draws from: its own pick from `config.mix` when
+    /// mixing, otherwise `language_key`.
+    fn language_key<'a>(&self, config: &'a Config, language_key: &'a str) -> &'a str {
+        match config.mix.len() {
+            0 => language_key,
+            len => &config.mix[self.language % len],
+        }
+    }
+
+    fn random_idle(config: &Config, rng: &mut impl Rng) -> u16 {
+        rng.gen_range(config.idle_min..=config.idle_max.max(config.idle_min))
+    }
+
+    pub fn resize(&mut self, height: u16) {
+        self.cells.resize(height as usize, Cell::default());
+        // Drops that were already past the new bottom edge would otherwise linger
+        // off-screen until their old exit point.
+        let cells = self.cells.len() as i16;
+        self.drops.retain(|drop| drop.head < cells + drop.len);
+    }
+
+    /// Adds a drop whose head appears at `row` with `char` on its next move.
+    /// Drops given the same `len` and `velocity` move in step.
+    pub fn spell(&mut self, row: i16, char: char, len: i16, velocity: f32) {
+        let velocity = velocity.min(1.0);
+        self.drops.push(Drop {
+            head: row - 1,
+            len,
+            velocity,
+            offset: 1.0 - velocity,
+            pending: Some(char),
+            ahead: None,
+            stride: 1,
+            lightning: false,
+            hot: 1,
+            bold: false,
+        });
+    }
+
+    fn is_head(&self, i: usize) -> bool {
+        self.drops.iter().any(|drop| drop.head == i as i16)
+    }
+
+    pub fn update(
+        &mut self,
+        config: &Config,
+        colors: &ColorScheme,
+        language_key: &str,
+        shading: Shading,
+        rng: &mut impl Rng,
+    ) {
+        let mut language_key = self.language_key(config, language_key);
+        // Trail glyphs flicker every frame, not just when a drop moves.
+        if config.mutation_rate > 0.0 {
+            for i in 0..self.cells.len() {
+                let cell = &self.cells[i];
+                if cell.lifetime > 0 && !cell.fixed && !self.is_head(i) && rng.gen_bool(config.mutation_rate) {
+                    self.cells[i].char = get_random_char(language_key, rng);
+                }
+            }
+        }
+
+        for cell in self.cells.iter_mut() {
+            cell.glint = false;
+            cell.age = cell.age.saturating_add(1);
+            if cell.lifetime == 0 && cell.ghost > 0 {
+                cell.ghost -= 1;
+                if cell.ghost == 0 {
+                    cell.char = ' ';
+                }
+            }
+        }
+
+        if self.idle > 0 {
+            self.idle -= 1;
+        }
+        // A new drop waits until the previous one has fully entered the screen.
+        let entry_clear = self.drops.last().is_none_or(|drop| drop.head >= drop.len);
+        let ready = self.idle == 0 && entry_clear && self.drops.len() < config.max_drops && !self.transient && !self.held;
+        if ready && rng.gen_bool(config.spawn_chance) {
+            if !config.mix.is_empty() && self.drops.is_empty() {
+                self.language = rng.gen_range(0..config.mix.len());
+                language_key = &config.mix[self.language];
+            }
+            let mut drop = Drop::new(self.cells.len() as u16, config, rng);
+            // A drop that caught up with the one ahead would run over its trail and head.
+            if let Some(ahead) = self.drops.last() {
+                drop.velocity = drop.velocity.min(ahead.velocity);
+            }
+            self.drops.push(drop);
+            if self.drops.len() < config.max_drops {
+                self.idle = Self::random_idle(config, rng);
+            }
+        }
+
+        let cells = &mut self.cells;
+        for drop in self.drops.iter_mut() {
+            drop.offset += drop.velocity;
+            if drop.offset < 1.0 - STEP_EPSILON {
+                continue;
+            }
+            drop.offset = (drop.offset - 1.0).max(0.0);
+            let mut ahead = drop.ahead.take();
+            let colors = &drop.colors(config, colors, shading);
+            let bright = drop.bright(config);
+            drop.hot = drop.head_cells(config, colors.name);
+
+            for _ in 0..drop.stride {
+                let trail = drop.trail(cells.len());
+                for cell in cells[trail.clone()].iter_mut() {
+                    if cell.lifetime > 0 {
+                        cell.lifetime -= 1;
+                        if cell.lifetime == 0 {
+                            if config.ghosts && !drop.lightning && rng.gen_bool(config.ghost_chance) {
+                                cell.ghost = config.ghost_frames;
+                                cell.color = ghost_color(colors, shading);
+                            } else {
+                                cell.char = ' ';
+                            }
+                        }
+                    }
+                }
+
+                drop.head += 1;
+                // Shades go by distance from the head, which keeps moving after it
+                // leaves the screen, so the tail fades out the same way it always has.
+                for i in trail {
+                    cells[i].color = if drop.remaining(i) > drop.len - drop.hot {
+                        colors.head
+                    } else {
+                        trail_color(colors, shading, drop.remaining(i), drop.len, bright)
+                    };
+                }
+                if drop.head >= 0 && drop.head < cells.len() as i16 {
+                    cells[drop.head as usize] = match drop.pending.take() {
+                        Some(char) => Cell { char, color: colors.head, lifetime: drop.len, glint: false, fixed: true, age: 0, ghost: 0 },
+                        None => {
+                            let glint = rng.gen_bool(config.glint_rate);
+                            Cell {
+                                char: ahead.take().map_or_else(|| get_random_char(language_key, rng), |(char, _)| char),
+                                color: if glint { colors.ink() } else { colors.head },
+                                lifetime: drop.len,
+                                glint,
+                                fixed: false,
+                                age: 0,
+                                ghost: 0,
+                            }
+                        }
+                    };
+                }
+            }
+        }
+
+        // Ramping up needs in-between shades, so only gradients fade heads in.
+        if config.head_fade_in > 0 && matches!(shading, Shading::Gradient { .. }) {
+            for drop in self.drops.iter().filter(|drop| !drop.lightning) {
+                if let Some(cell) = usize::try_from(drop.head).ok().and_then(|i| self.cells.get_mut(i))
+                    && !cell.glint
+                {
+                    let head = depth_colors(config, colors, drop.velocity, shading).head;
+                    let t = (cell.age + 1) as f32 / (config.head_fade_in + 1) as f32;
+                    cell.color = lerp(colors.base(), head, t.min(1.0));
+                }
+            }
+        }
+
+        // The cell a head is moving into brightens as the head gets closer,
+        // which needs in-between shades too.
+        if config.smooth_motion && matches!(shading, Shading::Gradient { .. }) {
+            let len = self.cells.len() as i16;
+            for drop in self.drops.iter_mut().filter(|drop| !drop.lightning) {
+                drop.ahead = (drop.head + 1 >= 0 && drop.head + 1 < len).then(|| {
+                    let char = drop
+                        .pending
+                        .or(drop.ahead.map(|(char, _)| char))
+                        .unwrap_or_else(|| get_random_char(language_key, rng));
+                    let head = depth_colors(config, colors, drop.velocity, shading).head;
+                    (char, lerp(colors.base(), head, drop.offset))
+                });
+            }
+        }
+
+        let before = self.drops.len();
+        let len = self.cells.len() as i16;
+        self.drops.retain(|drop| drop.head < len + drop.len);
+        if self.drops.len() < before && self.idle == 0 {
+            self.idle = Self::random_idle(config, rng);
+        }
+    }
+
+    /// Colors the live cells afresh from `colors`, as if they had fallen in it,
+    /// without moving anything.
+    pub fn repaint(&mut self, config: &Config, colors: &ColorScheme, shading: Shading) {
+        for cell in self.cells.iter_mut().filter(|cell| cell.lifetime == 0 && cell.ghost > 0) {
+            cell.color = ghost_color(colors, shading);
+        }
+        for drop in self.drops.iter_mut() {
+            let colors = &drop.colors(config, colors, shading);
+            let bright = drop.bright(config);
+            drop.hot = drop.head_cells(config, colors.name);
+            for i in drop.trail(self.cells.len()) {
+                let cell = &mut self.cells[i];
+                if cell.lifetime > 0 {
+                    cell.color = if cell.glint {
+                        colors.ink()
+                    } else if drop.remaining(i) > drop.len - drop.hot {
+                        colors.head
+                    } else {
+                        trail_color(colors, shading, drop.remaining(i), drop.len, bright)
+                    };
+                }
+            }
+            if let Some((_, color)) = &mut drop.ahead {
+                *color = lerp(colors.base(), colors.head, drop.offset);
+            }
+        }
+    }
+
+    /// Draws the live cells, placing cell `i` at the screen position `position(i)`.
+    pub fn draw(&self, config: &Config, frame: &mut Frame, position: impl Fn(usize) -> (u16, u16)) {
+        // Spelled-out characters, from messages and art, stay readable.
+        let glyph = |char, fixed: bool| if config.mirror && !fixed { mirrored(char) } else { char };
+        for (i, cell) in self.cells.iter().enumerate() {
+            if cell.lifetime == 0 && cell.ghost > 0 {
+                let (x, y) = position(i);
+                frame.set(x, y, Glyph { char: glyph(cell.char, cell.fixed), color: cell.color, dim: config.attributes, ..BLANK });
+            }
+        }
+        for drop in &self.drops {
+            if let Some((char, color)) = drop.ahead
+                && let Some(cell) = usize::try_from(drop.head + 1).ok().and_then(|i| self.cells.get(i))
+                && cell.lifetime == 0
+            {
+                let (x, y) = position((drop.head + 1) as usize);
+                frame.set(x, y, Glyph { char: glyph(char, drop.pending.is_some()), color, ..BLANK });
+            }
+            let bright = drop.bright(config);
+            for i in drop.trail(self.cells.len()) {
+                let cell = &self.cells[i];
+                if cell.lifetime > 0 {
+                    let is_head = i as i16 == drop.head;
+                    let bold = cell.glint || (config.attributes && (is_head || drop.bold));
+                    // Same split as the stepped trail/fade coloring.
+                    let dim = config.attributes
+                        && !cell.glint
+                        && !drop.bold
+                        && drop.remaining(i) <= drop.len - bright.max(drop.hot);
+                    let reverse = config.inverse_heads && is_head;
+                    let (x, y) = position(i);
+                    frame.set(x, y, Glyph { char: glyph(cell.char, cell.fixed), color: cell.color, bold, dim, reverse });
+                }
+            }
+        }
+    }
+}
+
+/// Spreads `density * slots` active slots evenly across the row: slot `i` is
+/// active when it crosses the next whole multiple of `1 / density`. A density of
+/// 0.5 gives every other slot starting with the first, 1.0 gives all of them.
+fn active_slots(slots: u16, density: f32) -> impl Iterator<Item = u16> {
+    let density = density.clamp(0.0, 1.0);
+    (0..slots).filter(move |&i| (((i + 1) as f32) * density).ceil() > (i as f32 * density).ceil())
+}
+
+/// The whole field of columns covering a `width` x `height` area.
+pub struct Rain {
+    pub columns: Vec<Column>,
+    /// Transient columns holding drops started by [`Rain::seed`] between the
+    /// regular ones, each kept until its drops have drained.
+    seeded: Vec<Column>,
+    /// The output can show arbitrary RGB colors, so trails may use gradients.
+    pub truecolor: bool,
+    /// The output has shades enough, 256 colors or more, for fading tails to
+    /// darken cell by cell instead of all sharing the fade color.
+    pub dissolve: bool,
+    width: u16,
+    height: u16,
+    slot_width: u16,
+    direction: Direction,
+    /// Current hue of the rainbow theme, in degrees.
+    hue: f32,
+    /// Scales the theme colors, 0.0 black to 1.0 as given.
+    pub brightness: f32,
+    /// Sideways shift built up by `wind_bias`, in slots (rows for horizontal rain).
+    wind_drift: f32,
+    /// Position in the wind's sway cycle, from 0.0 to 1.0.
+    wind_phase: f32,
+}
+
+impl Rain {
+    /// Divides the width into slots `slot_width` cells wide (see
+    /// [`crate::charset::slot_width`]) and puts columns in `config.density` of them.
+    pub fn new(width: u16, height: u16, slot_width: u16, config: &Config) -> Self {
+        let mut rain = Self {
+            columns: Vec::new(),
+            seeded: Vec::new(),
+            truecolor: false,
+            dissolve: false,
+            width,
+            height,
+            slot_width,
+            direction: config.direction,
+            hue: 0.0,
+            brightness: 1.0,
+            wind_drift: 0.0,
+            wind_phase: 0.0,
+        };
+        rain.resize(width, height, slot_width, config);
+        rain
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Number of cells a drop travels through from one edge to the other.
+    pub fn lane_len(&self) -> u16 {
+        if self.direction.is_vertical() {
+            self.height
+        } else {
+            self.width / self.slot_width
+        }
+    }
+
+    /// Re-lays out the columns for a new area, slot width, density or direction.
+    /// Existing columns keep their drops; columns added at the end start fresh.
+    /// Vertical rain puts columns in slots across the width; horizontal rain puts
+    /// them in rows and steps each one `slot_width` cells at a time.
+    pub fn resize(&mut self, width: u16, height: u16, slot_width: u16, config: &Config) {
+        if config.direction.is_vertical() != self.direction.is_vertical() {
+            self.columns.clear();
+        }
+        self.seeded.clear();
+        self.width = width;
+        self.height = height;
+        self.slot_width = slot_width;
+        self.direction = config.direction;
+
+        let lane_len = self.lane_len();
+        let xs = if self.direction.is_vertical() {
+            active_slots(width / slot_width, config.density).map(|slot| slot * slot_width).collect::<Vec<_>>()
+        } else {
+            active_slots(height, config.density).collect::<Vec<_>>()
+        };
+        self.columns.truncate(xs.len());
+        for (col, &x) in self.columns.iter_mut().zip(xs.iter()) {
+            col.x = x;
+            col.resize(lane_len);
+        }
+        for &x in &xs[self.columns.len()..] {
+            self.columns.push(Column::new(x, lane_len));
+        }
+    }
+
+    /// Starts a drop whose head shows `char` at screen position `(x, y)` on its
+    /// next move, and then falls on like any other. It joins the column at that
+    /// spot, or a transient one if no column is there. Positions outside the
+    /// rain are ignored.
+    pub fn seed(&mut self, x: u16, y: u16, char: char, config: &Config, rng: &mut impl Rng) {
+        let lane_len = self.lane_len();
+        let (across, i) = match self.direction {
+            Direction::Down => (x - x % self.slot_width, y),
+            Direction::Up => (x - x % self.slot_width, lane_len.saturating_sub(y + 1)),
+            Direction::Right => (y, x / self.slot_width),
+            Direction::Left => (y, lane_len.saturating_sub(x / self.slot_width + 1)),
+        };
+        if x >= self.width || y >= self.height || i >= lane_len {
+            return;
+        }
+        let (min_len, max_len) = config.len_range(lane_len);
+        let len = rng.gen_range(min_len..=max_len);
+        let velocity = config.random_velocity(rng);
+        let col = if let Some(index) = self.columns.iter().position(|col| col.x == across) {
+            &mut self.columns[index]
+        } else if let Some(index) = self.seeded.iter().position(|col| col.x == across) {
+            &mut self.seeded[index]
+        } else {
+            let mut col = Column::new(across, lane_len);
+            col.transient = true;
+            self.seeded.push(col);
+            self.seeded.last_mut().unwrap()
+        };
+        col.spell(i as i16, char, len, velocity);
+    }
+
+    /// Slots a column can sit in across the direction of travel.
+    fn slots(&self) -> u16 {
+        if self.direction.is_vertical() { self.width / self.slot_width } else { self.height }
+    }
+
+    /// Slots the wind had pushed the rain sideways `age` frames ago. Each cell
+    /// stays where it was blown when it appeared, so trails slant and sway.
+    fn wind_offset(&self, config: &Config, age: u16) -> i32 {
+        let seconds = age as f32 / config.frames_per_second() as f32;
+        let sway = config.wind_period.max(f32::EPSILON);
+        let phase = (self.wind_phase - seconds / sway) * std::f32::consts::TAU;
+        (self.wind_drift - config.wind_bias * seconds + config.wind * phase.sin()).round() as i32
+    }
+
+    /// `x` moved `offset` slots sideways, wrapping around the edges.
+    fn shift(&self, x: u16, offset: i32) -> u16 {
+        let slots = self.slots().max(1) as i32;
+        if self.direction.is_vertical() {
+            let slot = (x / self.slot_width) as i32;
+            (slot + offset).rem_euclid(slots) as u16 * self.slot_width
+        } else {
+            (x as i32 + offset).rem_euclid(slots) as u16
+        }
+    }
+
+    /// Screen position of cell `i` of the column at `x`.
+    fn position(&self, x: u16, i: usize) -> (u16, u16) {
+        let i = i as u16;
+        let last = self.lane_len().saturating_sub(1);
+        match self.direction {
+            Direction::Down => (x, i),
+            Direction::Up => (x, last - i),
+            Direction::Right => (i * self.slot_width, x),
+            Direction::Left => ((last - i) * self.slot_width, x),
+        }
+    }
+
+    /// Advances every column by one tick. The rainbow theme shifts its hue a
+    /// little every tick, and spreads one full cycle of hues across the screen.
+    pub fn update(&mut self, config: &Config, colors: &ColorScheme, language_key: &str, rng: &mut impl Rng) {
+        let shading = self.shading(config);
+        if colors.name == RAINBOW_THEME {
+            self.hue = (self.hue + config.rainbow_speed).rem_euclid(360.0);
+        }
+        if config.wind_bias != 0.0 || config.wind != 0.0 {
+            let seconds = 1.0 / config.frames_per_second() as f32;
+            self.wind_drift = (self.wind_drift + config.wind_bias * seconds).rem_euclid(self.slots().max(1) as f32);
+            self.wind_phase = (self.wind_phase + seconds / config.wind_period.max(f32::EPSILON)).rem_euclid(1.0);
+        }
+        match config.max_active {
+            Some(cap) => self.hold_columns(cap, rng),
+            None => self.columns.iter_mut().for_each(|col| col.held = false),
+        }
+        // No bolts through dimmed rain, where they would be far brighter than the rest.
+        if config.lightning && self.brightness >= 1.0 && rng.gen_bool(config.lightning_rate) {
+            self.strike(rng);
+        }
+        self.each_column(colors, |col, colors| col.update(config, colors, language_key, shading, rng));
+        self.seeded.retain(|col| !col.drops.is_empty());
+    }
+
+    /// Lets only as many resting columns start a drop as keeps the raining ones
+    /// within `cap`, picking them at random so no part of the screen is favored.
+    fn hold_columns(&mut self, cap: usize, rng: &mut impl Rng) {
+        let (mut resting, raining): (Vec<&mut Column>, Vec<&mut Column>) =
+            self.columns.iter_mut().partition(|col| col.drops.is_empty());
+        let room = cap.saturating_sub(raining.len());
+        // A column already raining may start more drops of its own.
+        for col in raining {
+            col.held = false;
+        }
+        resting.shuffle(rng);
+        for (i, col) in resting.into_iter().enumerate() {
+            col.held = i >= room;
+        }
+    }
+
+    /// Sends a lightning bolt down a random column: a drop as long as the
+    /// screen that crosses it in a couple of frames, bright all the way along.
+    pub fn strike(&mut self, rng: &mut impl Rng) {
+        if self.columns.is_empty() {
+            return;
+        }
+        let index = rng.gen_range(0..self.columns.len());
+        let col = &mut self.columns[index];
+        col.drops.push(Drop::lightning(col.cells.len() as u16));
+    }
+
+    /// Recolors what is on screen for `colors` and the current brightness
+    /// without advancing anything, so a frozen frame follows theme changes.
+    pub fn repaint(&mut self, config: &Config, colors: &ColorScheme) {
+        let shading = self.shading(config);
+        self.each_column(colors, |col, colors| col.repaint(config, colors, shading));
+    }
+
+    fn shading(&self, config: &Config) -> Shading {
+        if self.truecolor {
+            Shading::Gradient { steps: config.gradient_steps }
+        } else if self.dissolve {
+            Shading::Dissolving
+        } else {
+            Shading::Stepped
+        }
+    }
+
+    /// Calls `f` on every column with the colors it is drawn in: `colors`
+    /// dimmed to the current brightness, or for the rainbow theme, the hue at
+    /// that column.
+    fn each_column(&mut self, colors: &ColorScheme, mut f: impl FnMut(&mut Column, &ColorScheme)) {
+        let cycling = colors.name == RAINBOW_THEME;
+        let across = if self.direction.is_vertical() { self.width } else { self.height }.max(1) as f32;
+        let dimmed = (self.brightness < 1.0).then(|| colors.dimmed(self.brightness));
+        for col in self.columns.iter_mut().chain(self.seeded.iter_mut()) {
+            if cycling {
+                let colors = rainbow(self.hue + col.x as f32 / across * 360.0).dimmed(self.brightness);
+                f(col, &colors);
+            } else {
+                f(col, dimmed.as_ref().unwrap_or(colors));
+            }
+        }
+    }
+
+    /// Draws every live cell onto `frame`, leaving the rest of it untouched.
+    pub fn render_to(&self, config: &Config, frame: &mut Frame) {
+        let windy = config.wind_bias != 0.0 || config.wind != 0.0;
+        for col in self.columns.iter().chain(self.seeded.iter()) {
+            if windy {
+                col.draw(config, frame, |i| {
+                    // Empty cells are only drawn into ahead of a head, which is new.
+                    let cell = &col.cells[i];
+                    let age = if cell.lifetime > 0 || cell.ghost > 0 { cell.age } else { 0 };
+                    self.position(self.shift(col.x, self.wind_offset(config, age)), i)
+                });
+            } else {
+                col.draw(config, frame, |i| self.position(col.x, i));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::language_keys;
+    use crate::config::{BrightTrail, MAX_DROPS};
+    use crate::theme::THEMES;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Settings that leave a hand-placed drop alone: no mutation or glints,
+    /// and no new drops while it falls.
+    fn config() -> Config {
+        let mut config = Config::new(&language_keys());
+        config.mutation_rate = 0.0;
+        config.glint_rate = 0.0;
+        config.max_drops = 1;
+        config.idle_min = 5;
+        config.idle_max = 5;
+        config.bright_trail = BrightTrail::Cells(3);
+        config.start_offset_max = 0;
+        config
+    }
+
+    fn column(height: u16, len: i16, frames_per_move: u16) -> Column {
+        let mut column = Column::new(0, height);
+        let velocity = 1.0 / frames_per_move as f32;
+        column.drops.push(Drop { head: -1, len, velocity, offset: 0.0, pending: None, ahead: None, stride: 1, lightning: false, hot: 1, bold: false });
+        column
+    }
+
+    fn tick(column: &mut Column, config: &Config, rng: &mut StdRng) {
+        column.update(config, &THEMES[0], "English", Shading::Stepped, rng);
+    }
+
+    #[test]
+    fn drop_advances_once_per_frames_per_move() {
+        let config = config();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 5, 3);
+        for expected in 0..4 {
+            for _ in 0..3 {
+                tick(&mut column, &config, &mut rng);
+            }
+            assert_eq!(column.drops[0].head, expected);
+        }
+    }
+
+    #[test]
+    fn cells_expire_to_blank() {
+        let config = config();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 3, 1);
+        tick(&mut column, &config, &mut rng);
+        assert_eq!(column.cells[0].lifetime, 3);
+        assert_ne!(column.cells[0].char, ' ');
+        for _ in 0..3 {
+            tick(&mut column, &config, &mut rng);
+        }
+        assert_eq!(column.cells[0].lifetime, 0);
+        assert_eq!(column.cells[0].char, ' ');
+        assert_eq!(column.cells[1].lifetime, 1);
+    }
+
+    #[test]
+    fn ghosts_linger_then_clear() {
+        let mut config = config();
+        config.ghosts = true;
+        config.ghost_chance = 1.0;
+        config.ghost_frames = 2;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 3, 1);
+        tick(&mut column, &config, &mut rng);
+        let char = column.cells[0].char;
+        for _ in 0..3 {
+            tick(&mut column, &config, &mut rng);
+        }
+        assert_eq!(column.cells[0].lifetime, 0);
+        assert_eq!(column.cells[0].char, char);
+        assert_eq!(column.cells[0].color, ghost_color(&THEMES[0], Shading::Stepped));
+        for _ in 0..2 {
+            tick(&mut column, &config, &mut rng);
+        }
+        assert_eq!(column.cells[0].char, ' ');
+    }
+
+    #[test]
+    fn head_length_lights_cells_behind_the_head() {
+        let mut config = config();
+        config.head_length = 2;
+        config.theme_head_length.push((THEMES[1].name.to_string(), 3));
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 6, 1);
+        for _ in 0..6 {
+            tick(&mut column, &config, &mut rng);
+        }
+        let colors = &THEMES[0];
+        let shown: Vec<Color> = column.cells[2..6].iter().map(|c| c.color).collect();
+        assert_eq!(shown, [colors.fade, colors.trail, colors.head, colors.head]);
+
+        column.repaint(&config, &THEMES[1], Shading::Stepped);
+        let colors = &THEMES[1];
+        let shown: Vec<Color> = column.cells[2..6].iter().map(|c| c.color).collect();
+        assert_eq!(shown, [colors.fade, colors.head, colors.head, colors.head]);
+    }
+
+    #[test]
+    fn mirror_leaves_spelled_characters_alone() {
+        let mut config = config();
+        config.mirror = true;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut spelled = Column::new(0, 10);
+        spelled.spell(0, 'E', 3, 1.0);
+        let mut random = column(10, 3, 1);
+        tick(&mut spelled, &config, &mut rng);
+        tick(&mut random, &config, &mut rng);
+        random.cells[0].char = 'E';
+        let mut frame = Frame::new(2, 10);
+        spelled.draw(&config, &mut frame, |i| (0, i as u16));
+        random.draw(&config, &mut frame, |i| (1, i as u16));
+        assert_eq!(frame.get(0, 0).char, 'E');
+        assert_eq!(frame.get(1, 0).char, 'Ǝ');
+    }
+
+    #[test]
+    fn stepped_coloring_splits_three_cells_behind_head() {
+        let config = config();
+        let colors = &THEMES[0];
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 6, 1);
+        for _ in 0..6 {
+            tick(&mut column, &config, &mut rng);
+        }
+        let lifetimes: Vec<i16> = column.cells[..6].iter().map(|c| c.lifetime).collect();
+        assert_eq!(lifetimes, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(column.cells[5].color, colors.head);
+        assert_eq!(column.cells[4].color, colors.trail);
+        assert_eq!(column.cells[3].color, colors.trail);
+        assert_eq!(column.cells[2].color, colors.fade);
+        assert_eq!(column.cells[0].color, colors.fade);
+    }
+
+    #[test]
+    fn short_drops_keep_a_bright_cell_behind_the_head() {
+        let mut config = config();
+        config.bright_trail = BrightTrail::Fraction(0.25);
+        let colors = &THEMES[0];
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 5, 1);
+        for _ in 0..5 {
+            tick(&mut column, &config, &mut rng);
+        }
+        let shown: Vec<Color> = column.cells[..5].iter().map(|c| c.color).collect();
+        assert_eq!(shown, [colors.fade, colors.fade, colors.fade, colors.trail, colors.head]);
+    }
+
+    #[test]
+    fn dissolving_fade_darkens_toward_the_tail() {
+        let config = config();
+        let colors = &THEMES[0];
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 6, 1);
+        for _ in 0..6 {
+            column.update(&config, colors, "English", Shading::Dissolving, &mut rng);
+        }
+        assert_eq!(column.cells[4].color, colors.trail);
+        assert_eq!(column.cells[2].color, lerp(Color::Black, colors.fade, 3.0 / 6.0));
+        assert_eq!(column.cells[0].color, lerp(Color::Black, colors.fade, 1.0 / 6.0));
+    }
+
+    #[test]
+    fn cells_stay_small() {
+        // The per-cell memory budget in main.rs counts on these sizes.
+        assert_eq!(std::mem::size_of::<Cell>(), 16);
+        assert_eq!(std::mem::size_of::<Glyph>(), 12);
+    }
+
+    #[test]
+    fn light_theme_dissolves_into_its_background() {
+        let config = config();
+        let colors = THEMES.iter().find(|t| t.name == crate::theme::LIGHT_THEME).unwrap();
+        let background = colors.background.unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(20, 6, 1);
+        for _ in 0..6 {
+            column.update(&config, colors, "English", Shading::Dissolving, &mut rng);
+        }
+        assert_eq!(column.cells[0].color, lerp(background, colors.fade, 1.0 / 6.0));
+        assert_eq!(colors.dimmed(0.0).head, background);
+    }
+
+    #[test]
+    fn tail_keeps_fading_after_the_head_leaves() {
+        let config = config();
+        let colors = &THEMES[0];
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(4, 6, 1);
+        for _ in 0..6 {
+            tick(&mut column, &config, &mut rng);
+        }
+        // The head is two cells below the bottom.
+        assert_eq!(column.drops[0].head, 5);
+        let lifetimes: Vec<i16> = column.cells.iter().map(|c| c.lifetime).collect();
+        assert_eq!(lifetimes, [1, 2, 3, 4]);
+        assert_eq!(column.cells[3].color, colors.trail);
+        assert_eq!(column.cells[2].color, colors.fade);
+    }
+
+    #[test]
+    fn drop_ends_once_it_has_fully_exited() {
+        let config = config();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = column(4, 2, 1);
+        for _ in 0..6 {
+            tick(&mut column, &config, &mut rng);
+        }
+        // Every cell has expired, but the tail has not passed the bottom yet.
+        assert_eq!(column.drops.len(), 1);
+        assert_eq!(column.drops[0].head, 5);
+        tick(&mut column, &config, &mut rng);
+        assert!(column.drops.is_empty());
+        assert!(column.cells.iter().all(|c| c.lifetime == 0 && c.char == ' '));
+        assert_eq!(column.idle, 5);
+    }
+
+    #[test]
+    fn drops_in_one_column_stay_apart() {
+        let mut config = config();
+        config.max_drops = 3;
+        config.idle_min = 0;
+        config.idle_max = 0;
+        config.spawn_chance = 1.0;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut column = Column::new(0, 40);
+        for _ in 0..500 {
+            tick(&mut column, &config, &mut rng);
+            assert!(column.drops.len() <= config.max_drops);
+            for (drop, behind) in column.drops.iter().zip(&column.drops[1..]) {
+                assert!(behind.head <= drop.head - drop.len);
+            }
+            for drop in &column.drops {
+                if let Some(cell) = usize::try_from(drop.head).ok().and_then(|i| column.cells.get(i)) {
+                    assert_eq!(cell.lifetime, drop.len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn max_active_caps_the_raining_columns() {
+        let mut config = Config::new(&language_keys());
+        config.max_active = Some(4);
+        config.spawn_chance = 1.0;
+        config.max_drops = MAX_DROPS;
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut rain = Rain::new(40, 20, 1, &config);
+        let mut most = 0;
+        for _ in 0..500 {
+            rain.update(&config, &THEMES[0], "English", &mut rng);
+            let raining = rain.columns.iter().filter(|col| !col.drops.is_empty()).count();
+            assert!(raining <= 4);
+            most = most.max(raining);
+        }
+        assert_eq!(most, 4);
+    }
+
+    #[test]
+    fn tiny_fields_never_panic() {
+        let mut config = Config::new(&language_keys());
+        config.max_drops = MAX_DROPS;
+        config.smooth_motion = true;
+        config.wind = 3.0;
+        let mut rng = StdRng::seed_from_u64(1);
+        for (width, height) in [(0, 0), (1, 1), (0, 5), (5, 0), (1, 2), (2, 1)] {
+            for direction in Direction::ALL {
+                config.direction = direction;
+                let mut rain = Rain::new(width, height, 1, &config);
+                let mut frame = Frame::new(width, height);
+                rain.seed(0, 0, 'x', &config, &mut rng);
+                for _ in 0..50 {
+                    rain.update(&config, &THEMES[0], "English", &mut rng);
+                    frame.clear();
+                    rain.render_to(&config, &mut frame);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// An asciinema v2 recording: a JSON header line followed by one JSON array
+/// per event.
+struct Cast {
+    file: BufWriter<File>,
+    start: Instant,
+    pending: Vec<u8>,
+}
+
+impl Cast {
+    fn event(&mut self, kind: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.file, "[{:.6}, \"{}\", {}]", time, kind, serde_json::to_string(data)?)
+    }
+}
+
+/// Passes output through to `inner`, and when recording, also saves everything
+/// written between flushes as one output event of a `.cast` file.
+pub struct Recorder<W: Write> {
+    inner: W,
+    cast: Option<Cast>,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, cast: None }
+    }
+
+    /// Starts recording to `path` for a `width` x `height` terminal,
+    /// replacing anything already there.
+    pub fn record(&mut self, path: &Path, width: u16, height: u16) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        writeln!(file, "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}}}", width, height, timestamp)?;
+        self.cast = Some(Cast { file, start: Instant::now(), pending: Vec::new() });
+        Ok(())
+    }
+
+    /// Notes a terminal resize in the recording.
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<()> {
+        match &mut self.cast {
+            Some(cast) => cast.event("r", &format!("{}x{}", width, height)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(cast) = &mut self.cast {
+            cast.pending.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(cast) = &mut self.cast
+            && !cast.pending.is_empty()
+        {
+            let data = String::from_utf8_lossy(&cast.pending).into_owned();
+            cast.pending.clear();
+            cast.event("o", &data)?;
+            cast.file.flush()?;
+        }
+        self.inner.flush()
+    }
+}
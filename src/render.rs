@@ -0,0 +1,398 @@
+use crate::color::{to_rgb, Color};
+use crate::frame::Frame;
+#[cfg(feature = "terminal")]
+use crate::frame::{Glyph, BLANK, CONTINUATION};
+#[cfg(feature = "terminal")]
+use crossterm::{
+    cursor, queue,
+    style::{Attribute, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+    Command,
+};
+#[cfg(feature = "terminal")]
+use std::fmt;
+use std::io::IsTerminal;
+#[cfg(feature = "terminal")]
+use std::io::Write;
+
+/// Something that can show a [`Frame`]: a terminal, a test buffer, a canvas...
+pub trait Renderer {
+    fn render(&mut self, frame: &Frame) -> std::io::Result<()>;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorMode {
+    TrueColor,
+    Ansi256,
+    /// The 16 named colors only.
+    Ansi16,
+    /// No color escapes at all; everything is drawn in the terminal's default foreground.
+    Mono,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 4] = [ColorMode::TrueColor, ColorMode::Ansi256, ColorMode::Ansi16, ColorMode::Mono];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::TrueColor => "truecolor",
+            ColorMode::Ansi256 => "256",
+            ColorMode::Ansi16 => "16",
+            ColorMode::Mono => "mono",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Honors `NO_COLOR` (https://no-color.org) and falls back to monochrome when
+    /// stdout is not a terminal. Otherwise terminals advertise 24-bit support
+    /// through `$COLORTERM`, and 256 colors through a `$TERM` such as
+    /// `xterm-256color`; anything else gets the basic 16.
+    pub fn detect() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        if no_color || !std::io::stdout().is_terminal() {
+            return ColorMode::Mono;
+        }
+        if let Ok(v) = std::env::var("COLORTERM")
+            && (v == "truecolor" || v == "24bit")
+        {
+            return ColorMode::TrueColor;
+        }
+        match std::env::var("TERM") {
+            Ok(term) if term == "dumb" => ColorMode::Mono,
+            Ok(term) if term.contains("256") => ColorMode::Ansi256,
+            _ => ColorMode::Ansi16,
+        }
+    }
+
+    /// `color` as this mode can show it, mapping anything richer to the nearest
+    /// color the mode has.
+    pub fn apply(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorMode::Ansi256, Color::Rgb { r, g, b }) => Color::AnsiValue(rgb_to_ansi256(r, g, b)),
+            (ColorMode::Ansi16, Color::Rgb { .. } | Color::AnsiValue(_)) => match to_rgb(color) {
+                Some((r, g, b)) => rgb_to_ansi16(r, g, b),
+                None => color,
+            },
+            _ => color,
+        }
+    }
+}
+
+/// Sets the foreground or background color the way a [`ColorMode`] can show it.
+/// In 16-color mode this uses the classic SGR codes, since crossterm writes
+/// named colors as 256-color indexes that older terminals don't understand. In
+/// monochrome mode it writes nothing.
+#[cfg(feature = "terminal")]
+pub struct SetColor {
+    color: Color,
+    mode: ColorMode,
+    background: bool,
+}
+
+#[cfg(feature = "terminal")]
+impl SetColor {
+    pub fn foreground(mode: ColorMode, color: Color) -> Self {
+        Self { color, mode, background: false }
+    }
+
+    pub fn background(mode: ColorMode, color: Color) -> Self {
+        Self { color, mode, background: true }
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl Command for SetColor {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let color = self.mode.apply(self.color);
+        if self.mode == ColorMode::Mono {
+            return Ok(());
+        }
+        if self.mode == ColorMode::Ansi16
+            && let Some(code) = ansi16_code(color)
+        {
+            return write!(f, "\x1b[{}m", if self.background { code + 10 } else { code });
+        }
+        if self.background {
+            SetBackgroundColor(color).write_ansi(f)
+        } else {
+            SetForegroundColor(color).write_ansi(f)
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        let color = self.mode.apply(self.color);
+        if self.background {
+            SetBackgroundColor(color).execute_winapi()
+        } else {
+            SetForegroundColor(color).execute_winapi()
+        }
+    }
+}
+
+/// SGR foreground code of a named color; background codes are 10 higher.
+#[cfg(feature = "terminal")]
+fn ansi16_code(color: Color) -> Option<u8> {
+    let code = match color {
+        Color::Reset => 39,
+        Color::Black => 30,
+        Color::DarkRed => 31,
+        Color::DarkGreen => 32,
+        Color::DarkYellow => 33,
+        Color::DarkBlue => 34,
+        Color::DarkMagenta => 35,
+        Color::DarkCyan => 36,
+        Color::Grey => 37,
+        Color::DarkGrey => 90,
+        Color::Red => 91,
+        Color::Green => 92,
+        Color::Yellow => 93,
+        Color::Blue => 94,
+        Color::Magenta => 95,
+        Color::Cyan => 96,
+        Color::White => 97,
+        Color::Rgb { .. } | Color::AnsiValue(_) => return None,
+    };
+    Some(code)
+}
+
+/// Nearest of the 16 named colors, by their xterm default values.
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    const NAMED: [Color; 16] = [
+        Color::Black,
+        Color::DarkRed,
+        Color::DarkGreen,
+        Color::DarkYellow,
+        Color::DarkBlue,
+        Color::DarkMagenta,
+        Color::DarkCyan,
+        Color::Grey,
+        Color::DarkGrey,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+    ];
+    let distance = |color: Color| {
+        let (cr, cg, cb) = to_rgb(color).unwrap();
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    NAMED.into_iter().min_by_key(|&c| distance(c)).unwrap()
+}
+
+/// Nearest entry of the xterm 256-color palette, picking between the 6x6x6
+/// color cube and the 24-step grayscale ramp.
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest = |v: u8| (0..6).min_by_key(|&i| (CUBE[i] as i32 - v as i32).abs()).unwrap();
+    let (ri, gi, bi) = (nearest(r), nearest(g), nearest(b));
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let gray = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_step = if gray < 8 { 0 } else { ((gray - 8) / 10).min(23) };
+    let gray_value = 8 + gray_step * 10;
+
+    let distance = |cr: u32, cg: u32, cb: u32| {
+        let d = |a: u32, b: u8| (a as i32 - b as i32).pow(2);
+        d(cr, r) + d(cg, g) + d(cb, b)
+    };
+    let cube_distance = distance(CUBE[ri] as u32, CUBE[gi] as u32, CUBE[bi] as u32);
+    let gray_distance = distance(gray_value, gray_value, gray_value);
+    if gray_distance < cube_distance {
+        (232 + gray_step) as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Draws frames with crossterm escape sequences. It remembers what is already on
+/// screen and only emits the cells that differ from the previous frame.
+#[cfg(feature = "terminal")]
+pub struct TerminalRenderer<W: Write> {
+    out: W,
+    back: Vec<Glyph>,
+    width: u16,
+    height: u16,
+    full_redraw: bool,
+    color_mode: ColorMode,
+    background: Option<Color>,
+    /// Size of the terminal when the frame may not fit it.
+    visible: Option<(u16, u16)>,
+    /// Escapes that show a picture behind the rain, written after each clear.
+    backdrop: Option<String>,
+}
+
+/// Stands in for whatever a terminal without background color erase shows after
+/// a clear, so every cell gets painted with the background.
+#[cfg(feature = "terminal")]
+const UNKNOWN: Glyph = Glyph { char: '\u{FFFF}', ..BLANK };
+
+#[cfg(feature = "terminal")]
+impl<W: Write> TerminalRenderer<W> {
+    pub fn new(out: W, color_mode: ColorMode) -> Self {
+        Self {
+            out,
+            back: Vec::new(),
+            width: 0,
+            height: 0,
+            full_redraw: true,
+            color_mode,
+            background: None,
+            visible: None,
+            backdrop: None,
+        }
+    }
+
+    /// Only draws the cells that fit a `width` x `height` terminal, for frames
+    /// laid out at a size of their own.
+    pub fn set_visible(&mut self, width: u16, height: u16) {
+        self.visible = Some((width, height));
+        self.invalidate();
+    }
+
+    /// Fills the animation area with `background` instead of the terminal's
+    /// default. Ignored in monochrome mode.
+    pub fn set_background(&mut self, background: Option<Color>) {
+        self.background = background.filter(|_| self.color_mode != ColorMode::Mono);
+        self.invalidate();
+    }
+
+    /// Draws `backdrop`, the escapes for a terminal graphic, from the top left
+    /// corner whenever the screen is cleared, so the rain falls over it.
+    pub fn set_backdrop(&mut self, backdrop: Option<String>) {
+        self.backdrop = backdrop;
+        self.invalidate();
+    }
+
+    /// Forget what is on screen, e.g. after a menu or overlay has drawn over it.
+    pub fn invalidate(&mut self) {
+        self.full_redraw = true;
+    }
+
+    /// The underlying writer, for output that bypasses the frame such as menus.
+    pub fn writer(&mut self) -> &mut W {
+        &mut self.out
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn render(&mut self, frame: &Frame) -> std::io::Result<()> {
+        if (frame.width(), frame.height()) != (self.width, self.height) {
+            self.width = frame.width();
+            self.height = frame.height();
+            self.back = vec![BLANK; frame.glyphs().len()];
+            self.full_redraw = true;
+        }
+        if let Some(background) = self.background {
+            queue!(self.out, SetColor::background(self.color_mode, background))?;
+        }
+        if self.full_redraw {
+            queue!(self.out, Clear(ClearType::All))?;
+            if let Some(backdrop) = &self.backdrop {
+                queue!(self.out, cursor::MoveTo(0, 0), Print(backdrop))?;
+            }
+            self.back.fill(if self.background.is_some() { UNKNOWN } else { BLANK });
+            self.full_redraw = false;
+        }
+
+        let mut last_color = None;
+        let mut intensity = (false, false);
+        let mut reverse = false;
+        for (i, (front, back)) in frame.glyphs().iter().zip(self.back.iter()).enumerate() {
+            if front == back || *front == CONTINUATION {
+                continue;
+            }
+            let x = (i % self.width as usize) as u16;
+            let y = (i / self.width as usize) as u16;
+            if let Some((width, height)) = self.visible {
+                // A wide glyph cut by the edge would wrap onto the next line.
+                let wide = frame.glyphs().get(i + 1) == Some(&CONTINUATION);
+                if y >= height || x + wide as u16 >= width {
+                    continue;
+                }
+            }
+            queue!(self.out, cursor::MoveTo(x, y))?;
+            if self.color_mode != ColorMode::Mono && last_color != Some(front.color) {
+                queue!(self.out, SetColor::foreground(self.color_mode, front.color))?;
+                last_color = Some(front.color);
+            }
+            if (front.bold, front.dim) != intensity {
+                // Bold and dim share one SGR reset, so clear both and re-apply.
+                queue!(self.out, SetAttribute(Attribute::NormalIntensity))?;
+                if front.bold {
+                    queue!(self.out, SetAttribute(Attribute::Bold))?;
+                }
+                if front.dim {
+                    queue!(self.out, SetAttribute(Attribute::Dim))?;
+                }
+                intensity = (front.bold, front.dim);
+            }
+            if front.reverse != reverse {
+                let attribute = if front.reverse { Attribute::Reverse } else { Attribute::NoReverse };
+                queue!(self.out, SetAttribute(attribute))?;
+                reverse = front.reverse;
+            }
+            queue!(self.out, Print(front.char))?;
+        }
+        // Leave the terminal in its default style for menus and other output.
+        if intensity != (false, false) {
+            queue!(self.out, SetAttribute(Attribute::NormalIntensity))?;
+        }
+        if reverse {
+            queue!(self.out, SetAttribute(Attribute::NoReverse))?;
+        }
+        if self.background.is_some() {
+            queue!(self.out, SetColor::background(self.color_mode, Color::Reset))?;
+        }
+        self.back.copy_from_slice(frame.glyphs());
+        self.out.flush()
+    }
+}
+
+#[cfg(all(test, feature = "terminal"))]
+mod tests {
+    use super::*;
+
+    /// Renders `frame` and returns what was written since the last call.
+    fn output(renderer: &mut TerminalRenderer<Vec<u8>>, frame: &Frame) -> String {
+        renderer.render(frame).unwrap();
+        String::from_utf8(std::mem::take(renderer.writer())).unwrap()
+    }
+
+    #[test]
+    fn unchanged_frame_writes_nothing() {
+        let mut renderer = TerminalRenderer::new(Vec::new(), ColorMode::Mono);
+        let mut frame = Frame::new(4, 2);
+        frame.print(0, 0, "ab", Color::Green);
+        assert_eq!(output(&mut renderer, &frame), "\x1b[2J\x1b[1;1Ha\x1b[1;2Hb");
+        assert_eq!(output(&mut renderer, &frame), "");
+    }
+
+    #[test]
+    fn changed_cell_writes_a_move_and_the_glyph() {
+        let mut renderer = TerminalRenderer::new(Vec::new(), ColorMode::Mono);
+        let mut frame = Frame::new(4, 2);
+        frame.print(0, 0, "ab", Color::Green);
+        output(&mut renderer, &frame);
+        frame.print(2, 1, "c", Color::Green);
+        assert_eq!(output(&mut renderer, &frame), "\x1b[2;3Hc");
+    }
+
+    #[test]
+    fn smaller_visible_area_clips_the_frame() {
+        let mut renderer = TerminalRenderer::new(Vec::new(), ColorMode::Mono);
+        let mut frame = Frame::new(4, 2);
+        frame.print(0, 0, "abcd", Color::Green);
+        frame.print(0, 1, "efgh", Color::Green);
+        renderer.set_visible(2, 1);
+        assert_eq!(output(&mut renderer, &frame), "\x1b[2J\x1b[1;1Ha\x1b[1;2Hb");
+    }
+}
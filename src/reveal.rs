@@ -0,0 +1,75 @@
+use crate::color::Color;
+use crate::config::Config;
+use crate::frame::Frame;
+use crate::rain::Rain;
+use rand::Rng;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+
+/// One character of the art, placed relative to the art's top-left corner.
+struct ArtCell {
+    x: u16,
+    y: u16,
+    char: char,
+    release: Instant,
+}
+
+/// A block of text or ASCII art held still over the rain, then let go a
+/// character at a time into falling drops, so it melts into the matrix.
+pub struct Reveal {
+    cells: Vec<ArtCell>,
+    width: u16,
+    height: u16,
+}
+
+impl Reveal {
+    /// Holds `art` from `start` for `hold`, then releases each character at a
+    /// random moment during the following `melt`.
+    pub fn new(art: &str, start: Instant, hold: Duration, melt: Duration, rng: &mut impl Rng) -> Self {
+        let mut cells = Vec::new();
+        let mut width = 0;
+        let mut height = 0;
+        for (y, line) in art.lines().enumerate() {
+            let mut x = 0;
+            for char in line.chars() {
+                let advance = char.width().unwrap_or(0) as u16;
+                if !char.is_whitespace() && advance > 0 {
+                    let release = start + hold + melt.mul_f64(rng.gen_range(0.0..=1.0));
+                    cells.push(ArtCell { x, y: y as u16, char, release });
+                    height = y as u16 + 1;
+                }
+                x += advance;
+            }
+            width = width.max(x);
+        }
+        Self { cells, width, height }
+    }
+
+    /// Every character has been released.
+    pub fn is_done(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Top-left corner that centers the art in a `width` x `height` area.
+    fn origin(&self, width: u16, height: u16) -> (u16, u16) {
+        (width.saturating_sub(self.width) / 2, height.saturating_sub(self.height) / 2)
+    }
+
+    /// Hands the characters that are due over to `rain` as new drops.
+    pub fn update(&mut self, rain: &mut Rain, config: &Config, now: Instant, rng: &mut impl Rng) {
+        let (left, top) = self.origin(rain.width(), rain.height());
+        for cell in self.cells.iter().filter(|cell| cell.release <= now) {
+            rain.seed(left + cell.x, top + cell.y, cell.char, config, rng);
+        }
+        self.cells.retain(|cell| cell.release > now);
+    }
+
+    /// Draws the characters still held, over whatever is on `frame`.
+    pub fn draw(&self, frame: &mut Frame, color: Color) {
+        let (left, top) = self.origin(frame.width(), frame.height());
+        let mut buf = [0; 4];
+        for cell in &self.cells {
+            frame.print(left + cell.x, top + cell.y, cell.char.encode_utf8(&mut buf), color);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::frame::{Frame, CONTINUATION};
+#[cfg(feature = "terminal")]
+use crate::render::{ColorMode, SetColor};
+#[cfg(feature = "terminal")]
+use crossterm::queue;
+#[cfg(feature = "terminal")]
+use crossterm::style::{Attribute, Color, Print, SetAttribute};
+
+/// What a screenshot keeps of the frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScreenshotFormat {
+    /// Characters only.
+    Text,
+    /// Characters with the escape codes to reproduce their colors and
+    /// attributes when printed to a terminal.
+    Ansi,
+}
+
+impl ScreenshotFormat {
+    pub const ALL: [ScreenshotFormat; 2] = [ScreenshotFormat::Text, ScreenshotFormat::Ansi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScreenshotFormat::Text => "text",
+            ScreenshotFormat::Ansi => "ansi",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ScreenshotFormat::Text => "txt",
+            ScreenshotFormat::Ansi => "ans",
+        }
+    }
+}
+
+/// The frame as lines of text, with trailing blanks trimmed.
+pub fn to_text(frame: &Frame) -> String {
+    let mut text = String::new();
+    for row in frame.glyphs().chunks(frame.width().max(1) as usize) {
+        let line: String = row.iter().filter(|g| **g != CONTINUATION).map(|g| g.char).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// The frame as lines of text with inline color and attribute codes in
+/// `color_mode`. It only moves down line by line, so `cat` shows it where the
+/// cursor is rather than taking over the screen.
+#[cfg(feature = "terminal")]
+pub fn to_ansi(frame: &Frame, color_mode: ColorMode) -> String {
+    let mut out = Vec::new();
+    for row in frame.glyphs().chunks(frame.width().max(1) as usize) {
+        let mut color = Color::Reset;
+        let mut intensity = (false, false);
+        for glyph in row.iter().filter(|g| **g != CONTINUATION) {
+            // Blanks look the same in any color or intensity, so leave the
+            // current ones in place rather than toggling around every gap.
+            if glyph.char == ' ' {
+                let _ = queue!(out, Print(' '));
+                continue;
+            }
+            if color_mode != ColorMode::Mono {
+                let glyph_color = color_mode.apply(glyph.color);
+                if glyph_color != color {
+                    color = glyph_color;
+                    let _ = queue!(out, SetColor::foreground(color_mode, color));
+                }
+            }
+            if (glyph.bold, glyph.dim) != intensity {
+                intensity = (glyph.bold, glyph.dim);
+                let _ = queue!(out, SetAttribute(Attribute::NormalIntensity));
+                if glyph.bold {
+                    let _ = queue!(out, SetAttribute(Attribute::Bold));
+                }
+                if glyph.dim {
+                    let _ = queue!(out, SetAttribute(Attribute::Dim));
+                }
+            }
+            let _ = queue!(out, Print(glyph.char));
+        }
+        let _ = queue!(out, SetAttribute(Attribute::Reset), Print('\n'));
+    }
+    String::from_utf8(out).unwrap()
+}
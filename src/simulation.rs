@@ -0,0 +1,258 @@
+use crate::charset::{language_keys, layout_slot_width};
+use crate::config::Config;
+use crate::frame::Frame;
+use crate::glitch::Glitch;
+use crate::message::Message;
+use crate::rain::Rain;
+use crate::render::Renderer;
+use crate::reveal::Reveal;
+use crate::theme::{ColorScheme, RAINBOW_THEME, THEMES};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// How long the colors take to cross-fade to a newly picked theme.
+const THEME_FADE_DURATION: Duration = Duration::from_millis(400);
+
+/// How long the rain takes to fade out on quit, and in how many frames.
+const FADE_OUT_DURATION: Duration = Duration::from_millis(400);
+const FADE_OUT_FRAMES: u32 = 10;
+
+/// The rain on its own, with no output: each step advances it one tick and
+/// draws it into a [`Frame`], for front ends, tests and tools to pull frames
+/// from at their own pace and show however they like. Time is simulated at
+/// the frame rate, so a seeded run comes out the same every time.
+///
+/// ```
+/// use rusty_matrix::{language_keys, Config};
+/// use rusty_matrix::simulation::Simulation;
+///
+/// let mut config = Config::new(&language_keys());
+/// config.seed = Some(7);
+/// for frame in Simulation::new(80, 24, config).take(3) {
+///     assert_eq!(frame.width(), 80);
+/// }
+/// ```
+pub struct Simulation {
+    config: Config,
+    /// The colors the rain falls in; starts as the theme `config` names.
+    pub colors: ColorScheme,
+    language_key: String,
+    rain: Rain,
+    frame: Frame,
+    rng: StdRng,
+    effects: Effects,
+    /// When the first frame falls, and how many have since.
+    start: Instant,
+    frames: u32,
+}
+
+impl Simulation {
+    /// Rain over `width` x `height` cells in `config`'s theme and character
+    /// set, seeded from `config.seed` when set so runs can be repeated.
+    pub fn new(width: u16, height: u16, config: Config) -> Self {
+        let keys = language_keys();
+        let language_key = keys[config.language_index.min(keys.len() - 1)].clone();
+        let rain = Rain::new(width, height, layout_slot_width(&config, &keys), &config);
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let colors = THEMES.get(config.theme_index).copied().unwrap_or(THEMES[0]);
+        let start = Instant::now();
+        Self {
+            effects: Effects::new(&config, &colors, start),
+            colors,
+            config,
+            language_key,
+            rain,
+            frame: Frame::new(width, height),
+            rng,
+            start,
+            frames: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The rain itself, e.g. to set `truecolor` or `brightness`.
+    pub fn rain_mut(&mut self) -> &mut Rain {
+        &mut self.rain
+    }
+
+    /// Shows `art` in the rain, held for `hold` and then melted over `melt`.
+    pub fn reveal(&mut self, art: &str, hold: Duration, melt: Duration) {
+        self.effects.reveal(art, hold, melt, &mut self.rng);
+    }
+
+    /// Lays the rain out again for a new size.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        let slot_width = layout_slot_width(&self.config, &language_keys());
+        self.rain.resize(width, height, slot_width, &self.config);
+        self.frame.resize(width, height);
+    }
+
+    /// Advances the rain one tick and returns it drawn.
+    pub fn next_frame(&mut self) -> &Frame {
+        let now = self.start + self.config.frame_interval() * self.frames;
+        self.frames += 1;
+        let colors = self.effects.colors(&self.config, &self.colors, self.rain.truecolor, now);
+        self.effects.advance(&mut self.rain, &self.config, &colors, &self.language_key, now, &mut self.rng);
+        self.frame.clear();
+        self.effects.draw(&self.rain, &self.config, &mut self.frame, &colors, &self.language_key, &mut self.rng);
+        &self.frame
+    }
+
+    /// The frame drawn by the last step, blank before the first.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+/// Never runs out: every call is one more tick.
+impl Iterator for Simulation {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        Some(self.next_frame().clone())
+    }
+}
+
+/// What happens to the rain besides falling: the message spelled into it,
+/// art shown and melted, glitches, cross-fades between themes and the hue
+/// shift. The terminal and [`Simulation`] both step the rain through it, so
+/// a dump comes out the way the same run looks on screen.
+pub struct Effects {
+    /// When the run started, for the hue shift.
+    start: Instant,
+    message: Option<Message>,
+    next_message: Instant,
+    reveal: Option<Reveal>,
+    glitch: Glitch,
+    /// The theme and colors last drawn in, before the hue shift, and where a
+    /// cross-fade started from and when.
+    shown_theme: usize,
+    shown_colors: ColorScheme,
+    theme_fade: Option<(ColorScheme, Instant)>,
+    /// Holds the rain still: it picks up new colors, but nothing falls,
+    /// spells or glitches.
+    pub frozen: bool,
+}
+
+impl Effects {
+    /// Effects for a run starting at `start` in `colors`, with `config`'s message.
+    pub fn new(config: &Config, colors: &ColorScheme, start: Instant) -> Self {
+        Self {
+            start,
+            message: config.message.as_deref().map(Message::new),
+            next_message: start + Duration::from_secs(config.message_interval),
+            reveal: None,
+            glitch: Glitch::new(),
+            shown_theme: config.theme_index,
+            shown_colors: *colors,
+            theme_fade: None,
+            frozen: false,
+        }
+    }
+
+    /// Shows `art` from the start of the run, held for `hold` and then melted over `melt`.
+    pub fn reveal(&mut self, art: &str, hold: Duration, melt: Duration, rng: &mut impl Rng) {
+        self.reveal = Some(Reveal::new(art, self.start, hold, melt, rng));
+    }
+
+    /// The colors to draw in at `now`: `target`, cross-faded in from the last
+    /// colors drawn when `config.theme_index` has just changed, then turned
+    /// by the hue shift. Both need `truecolor`.
+    pub fn colors(&mut self, config: &Config, target: &ColorScheme, truecolor: bool, now: Instant) -> ColorScheme {
+        if config.theme_index != self.shown_theme {
+            self.shown_theme = config.theme_index;
+            // Rainbow colors change across the screen, so there's no one set to fade from or to.
+            let rainbow = self.shown_colors.name == RAINBOW_THEME || target.name == RAINBOW_THEME;
+            if config.theme_fade && truecolor && !rainbow {
+                self.theme_fade = Some((self.shown_colors, now));
+            }
+        }
+        let colors = match self.theme_fade {
+            Some((from, start)) if now < start + THEME_FADE_DURATION => {
+                let t = (now - start).as_secs_f32() / THEME_FADE_DURATION.as_secs_f32();
+                from.blend(target, t)
+            }
+            _ => {
+                self.theme_fade = None;
+                *target
+            }
+        };
+        self.shown_colors = colors;
+        hue_shift(config, &colors, truecolor, now - self.start).unwrap_or(colors)
+    }
+
+    /// Spells the message and moves the art along when they're due, then
+    /// advances the rain one tick in `colors`.
+    pub fn advance(&mut self, rain: &mut Rain, config: &Config, colors: &ColorScheme, language_key: &str, now: Instant, rng: &mut impl Rng) {
+        // A frozen frame stays put but still picks up theme changes.
+        if self.frozen {
+            rain.repaint(config, colors);
+            return;
+        }
+        if let Some(message) = &mut self.message
+            && now >= self.next_message
+        {
+            message.show(rain, config, rng);
+            self.next_message = now + Duration::from_secs(config.message_interval);
+        }
+        if let Some(art) = &mut self.reveal {
+            art.update(rain, config, now, rng);
+        }
+        rain.update(config, colors, language_key, rng);
+    }
+
+    /// Draws the rain into `frame`, the art over it and glitches over both.
+    pub fn draw(&mut self, rain: &Rain, config: &Config, frame: &mut Frame, colors: &ColorScheme, language_key: &str, rng: &mut impl Rng) {
+        rain.render_to(config, frame);
+        if let Some(art) = &self.reveal {
+            art.draw(frame, colors.head);
+        }
+        self.reveal = self.reveal.take().filter(|art| !art.is_done());
+        if config.glitch && !self.frozen {
+            self.glitch.update(config, frame.height(), rng);
+            self.glitch.apply(config, frame, colors, language_key, rng);
+        }
+    }
+}
+
+/// `colors` turned as far around the color wheel as `--hue-shift` has got
+/// after `elapsed`, or `None` when it is off or can't show. The rainbow theme
+/// already goes around it.
+fn hue_shift(config: &Config, colors: &ColorScheme, truecolor: bool, elapsed: Duration) -> Option<ColorScheme> {
+    if !config.hue_shift || !truecolor || colors.name == RAINBOW_THEME {
+        return None;
+    }
+    Some(colors.rotated(elapsed.as_secs_f32() * config.hue_shift_speed))
+}
+
+/// Lets the drops already falling carry on while they dim to black, with no
+/// new ones starting, so quitting doesn't just cut the rain off.
+pub fn fade_out(
+    renderer: &mut impl Renderer,
+    rain: &mut Rain,
+    frame: &mut Frame,
+    config: &Config,
+    colors: &ColorScheme,
+    language_key: &str,
+    rng: &mut impl Rng,
+) -> std::io::Result<()> {
+    let config = Config { spawn_chance: 0.0, ..config.clone() };
+    // Paused rain fades from its dimmed level.
+    let start = rain.brightness;
+    for i in (0..FADE_OUT_FRAMES).rev() {
+        rain.brightness = start * i as f32 / FADE_OUT_FRAMES as f32;
+        rain.update(&config, colors, language_key, rng);
+        frame.clear();
+        rain.render_to(&config, frame);
+        renderer.render(frame)?;
+        std::thread::sleep(FADE_OUT_DURATION / FADE_OUT_FRAMES);
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+/// Frames drawn per second and the average time spent producing each one,
+/// averaged over one-second windows so the numbers are steady enough to read.
+/// When draws are capped below the rate the rain steps at, the step rate is
+/// shown too.
+pub struct FrameStats {
+    window_start: Instant,
+    steps: u32,
+    draws: u32,
+    busy: Duration,
+    text: String,
+}
+
+impl FrameStats {
+    pub fn new(now: Instant) -> Self {
+        Self { window_start: now, steps: 0, draws: 0, busy: Duration::ZERO, text: "-- fps".to_string() }
+    }
+
+    /// Counts a step of the rain that took `busy` to update and, if `drawn`, draw.
+    pub fn record(&mut self, busy: Duration, drawn: bool, now: Instant) {
+        self.steps += 1;
+        if drawn {
+            self.draws += 1;
+        }
+        self.busy += busy;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            let seconds = elapsed.as_secs_f64();
+            let fps = self.draws as f64 / seconds;
+            let frame_ms = self.busy.as_secs_f64() * 1000.0 / self.draws.max(1) as f64;
+            self.text = if self.draws == self.steps {
+                format!("{:.0} fps {:.2} ms", fps, frame_ms)
+            } else {
+                format!("{:.0} fps ({:.0} steps/s) {:.2} ms", fps, self.steps as f64 / seconds, frame_ms)
+            };
+            self.window_start = now;
+            self.steps = 0;
+            self.draws = 0;
+            self.busy = Duration::ZERO;
+        }
+    }
+
+    /// The figures from the last complete window.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
@@ -0,0 +1,144 @@
+use crate::color::{from_hsv, lerp, rotate_hue, to_rgb, Color};
+use crate::config::normalize_name;
+
+#[derive(Clone, Copy)]
+pub struct ColorScheme {
+    pub name: &'static str,
+    pub head: Color,
+    pub trail: Color,
+    pub fade: Color,
+    /// Painted behind the rain unless `--bg` gives another; `None` keeps the
+    /// terminal's own, taken to be dark.
+    pub background: Option<Color>,
+}
+
+pub const THEMES: [ColorScheme; 7] = [
+    ColorScheme { name: "Classic Green", head: Color::White,   trail: Color::Green,      fade: Color::DarkGreen,   background: None },
+    ColorScheme { name: "Ocean Blue",    head: Color::White,   trail: Color::Blue,       fade: Color::DarkBlue,    background: None },
+    ColorScheme { name: "Crimson Red",   head: Color::White,   trail: Color::Red,        fade: Color::DarkRed,     background: None },
+    ColorScheme { name: "Cyberpunk",     head: Color::Cyan,    trail: Color::Magenta,    fade: Color::DarkMagenta, background: None },
+    ColorScheme {
+        name: "Phosphor",
+        head: Color::Rgb { r: 215, g: 255, b: 215 },
+        trail: Color::Rgb { r: 0, g: 235, b: 75 },
+        fade: Color::Rgb { r: 0, g: 100, b: 30 },
+        background: None,
+    },
+    // Placeholder colors; the rain computes the real ones with `rainbow`.
+    ColorScheme { name: RAINBOW_THEME, head: Color::White,   trail: Color::Red,        fade: Color::DarkRed,     background: None },
+    // Dark ink on paper: heads are darkest and trails fade out toward the page.
+    ColorScheme {
+        name: LIGHT_THEME,
+        head: Color::Rgb { r: 0, g: 30, b: 10 },
+        trail: Color::Rgb { r: 0, g: 120, b: 45 },
+        fade: Color::Rgb { r: 120, g: 185, b: 140 },
+        background: Some(Color::Rgb { r: 250, g: 250, b: 240 }),
+    },
+];
+
+impl ColorScheme {
+    /// The same scheme at `brightness`, from 0.0, faded into the background,
+    /// to 1.0, unchanged.
+    pub fn dimmed(&self, brightness: f32) -> ColorScheme {
+        let dim = |color| lerp(self.base(), color, brightness);
+        ColorScheme { head: dim(self.head), trail: dim(self.trail), fade: dim(self.fade), ..*self }
+    }
+
+    /// `t` of the way from this scheme to `to`, for cross-fading between
+    /// themes. The background has no in-between and is `to`'s throughout.
+    pub fn blend(&self, to: &ColorScheme, t: f32) -> ColorScheme {
+        ColorScheme {
+            name: to.name,
+            head: lerp(self.head, to.head, t),
+            trail: lerp(self.trail, to.trail, t),
+            fade: lerp(self.fade, to.fade, t),
+            background: to.background,
+        }
+    }
+
+    /// The scheme with head, trail and fade all turned `degrees` around the
+    /// color wheel, so they keep their relation to each other. The background
+    /// stays as it is.
+    pub fn rotated(&self, degrees: f32) -> ColorScheme {
+        let turn = |color| rotate_hue(color, degrees);
+        ColorScheme { head: turn(self.head), trail: turn(self.trail), fade: turn(self.fade), ..*self }
+    }
+
+    /// What the rain fades into: the theme's background, or black.
+    pub fn base(&self) -> Color {
+        self.background.unwrap_or(Color::Black)
+    }
+
+    /// Whether the rain falls on a light background and so darkens to stand out.
+    pub fn is_light(&self) -> bool {
+        to_rgb(self.base()).is_some_and(|(r, g, b)| r as u32 + g as u32 + b as u32 > 3 * 128)
+    }
+
+    /// The color that stands out most from the background, for flashes and
+    /// text drawn over the rain: white, or black on a light theme.
+    pub fn ink(&self) -> Color {
+        if self.is_light() { Color::Black } else { Color::White }
+    }
+}
+
+pub const CUSTOM_THEME: &str = "Custom";
+
+/// Light-background theme that `--light` picks.
+pub const LIGHT_THEME: &str = "Daylight";
+
+/// Theme whose colors cycle through the hues over time and across the screen.
+pub const RAINBOW_THEME: &str = "Rainbow";
+
+/// The rainbow theme's colors at `hue` degrees: a pale tint for the head, the
+/// pure hue for the trail and a darker shade of it for the fade.
+pub fn rainbow(hue: f32) -> ColorScheme {
+    ColorScheme {
+        name: RAINBOW_THEME,
+        head: from_hsv(hue, 0.25, 1.0),
+        trail: from_hsv(hue, 1.0, 1.0),
+        fade: from_hsv(hue, 1.0, 0.4),
+        background: None,
+    }
+}
+
+pub fn find_theme(name: &str, themes: &[ColorScheme]) -> Option<usize> {
+    let name = normalize_name(name);
+    themes.iter().position(|t| normalize_name(t.name) == name)
+}
+
+/// Parses `#RRGGBB` (the `#` is optional) into an RGB color.
+pub fn parse_hex_color(s: &str) -> Result<Color, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected a color like #00ff41, got '{}'", s));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok(Color::Rgb { r: channel(0), g: channel(2), b: channel(4) })
+}
+
+pub fn format_hex_color(color: Color) -> Option<String> {
+    match color {
+        Color::Rgb { r, g, b } => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(scheme: &ColorScheme) -> (Color, Color, Color, Option<Color>) {
+        (scheme.head, scheme.trail, scheme.fade, scheme.background)
+    }
+
+    #[test]
+    fn rotated_turns_the_rain_but_not_the_background() {
+        let scheme = ColorScheme { background: Some(Color::Rgb { r: 40, g: 0, b: 0 }), ..THEMES[2] };
+        assert_eq!(colors(&scheme.rotated(0.0)), colors(&scheme));
+        assert_eq!(colors(&scheme.rotated(360.0)), colors(&scheme));
+        let turned = scheme.rotated(120.0);
+        let green = (Color::White, Color::Rgb { r: 0, g: 255, b: 0 }, Color::Rgb { r: 0, g: 128, b: 0 }, scheme.background);
+        assert_eq!(colors(&turned), green);
+        assert_eq!(turned.name, scheme.name);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::baseline::Baseline;
+use crate::config::{Config, MAX_FPS};
+use std::time::Instant;
+
+/// What a density wave swells and ebbs.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WaveTarget {
+    /// How often new drops start.
+    Spawn,
+    /// How fast the rain falls.
+    Speed,
+    Both,
+}
+
+impl WaveTarget {
+    pub const ALL: [WaveTarget; 3] = [WaveTarget::Spawn, WaveTarget::Speed, WaveTarget::Both];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WaveTarget::Spawn => "spawn",
+            WaveTarget::Speed => "speed",
+            WaveTarget::Both => "both",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn spawn(self) -> bool {
+        self != WaveTarget::Speed
+    }
+
+    fn speed(self) -> bool {
+        self != WaveTarget::Spawn
+    }
+}
+
+/// Makes the rain breathe: the spawn chance and frame rate follow a sine
+/// around the picked ones, rising by up to `wave_amplitude` of them and
+/// falling as far, once every `wave_period` seconds.
+pub struct Wave {
+    start: Instant,
+}
+
+impl Wave {
+    pub fn new(now: Instant) -> Self {
+        Self { start: now }
+    }
+
+    /// Sets `config` for where the wave is at `now`, around `baseline`. Speed
+    /// moves by frame rate, which is finer than speed levels, so this sets `fps`.
+    pub fn apply(&self, baseline: &Baseline, config: &mut Config, now: Instant) {
+        let phase = now.duration_since(self.start).as_secs_f32() / config.wave_period.max(f32::EPSILON);
+        let swing = config.wave_amplitude * (phase * std::f32::consts::TAU).sin();
+        if config.wave_target.spawn() {
+            config.spawn_chance = (baseline.spawn_chance * (1.0 + swing as f64)).clamp(0.0, 1.0);
+        }
+        if config.wave_target.speed() {
+            let fps = baseline.frames_per_second() as f32 * (1.0 + swing);
+            config.fps = Some((fps.round() as u32).clamp(1, MAX_FPS));
+        }
+    }
+}
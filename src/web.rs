@@ -0,0 +1,130 @@
+use crate::charset::language_keys;
+use crate::color::{to_rgb, Color};
+use crate::config::Config;
+use crate::frame::{Frame, BLANK, CONTINUATION};
+use crate::render::Renderer;
+use crate::simulation::Simulation;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+/// Drawn for cells that use the default foreground.
+const DEFAULT_FOREGROUND: &str = "#c0c0c0";
+const BACKGROUND: &str = "#000000";
+
+/// How faint dim cells are drawn, as canvas has no dim text.
+const DIM_ALPHA: f64 = 0.6;
+
+/// Draws frames onto a `<canvas>`, one monospace character per cell. Canvas
+/// keeps no text to diff against, so every frame is painted from scratch.
+pub struct CanvasRenderer {
+    context: CanvasRenderingContext2d,
+    font_size: f64,
+    cell_width: f64,
+}
+
+fn js_error(e: JsValue) -> std::io::Error {
+    std::io::Error::other(format!("{:?}", e))
+}
+
+fn css_color(color: Color) -> String {
+    match to_rgb(color) {
+        Some((r, g, b)) => format!("rgb({},{},{})", r, g, b),
+        None => DEFAULT_FOREGROUND.to_string(),
+    }
+}
+
+impl CanvasRenderer {
+    pub fn new(canvas: &HtmlCanvasElement, font_size: f64) -> Result<Self, JsValue> {
+        let context: CanvasRenderingContext2d =
+            canvas.get_context("2d")?.ok_or("canvas has no 2d context")?.dyn_into()?;
+        context.set_font(&format!("{}px monospace", font_size));
+        let cell_width = context.measure_text("M")?.width().ceil().max(1.0);
+        Ok(Self { context, font_size, cell_width })
+    }
+
+    /// Cells that fit on `canvas` at this font size.
+    pub fn grid_size(&self, canvas: &HtmlCanvasElement) -> (u16, u16) {
+        let columns = (canvas.width() as f64 / self.cell_width) as u16;
+        let rows = (canvas.height() as f64 / self.font_size) as u16;
+        (columns, rows)
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn render(&mut self, frame: &Frame) -> std::io::Result<()> {
+        let context = &self.context;
+        let width = frame.width() as f64 * self.cell_width;
+        let height = frame.height() as f64 * self.font_size;
+        context.set_fill_style_str(BACKGROUND);
+        context.fill_rect(0.0, 0.0, width, height);
+        context.set_text_baseline("top");
+
+        let mut bold = false;
+        context.set_font(&format!("{}px monospace", self.font_size));
+        for (i, glyph) in frame.glyphs().iter().enumerate() {
+            if *glyph == BLANK || *glyph == CONTINUATION || glyph.char == ' ' {
+                continue;
+            }
+            let x = (i % frame.width() as usize) as f64 * self.cell_width;
+            let y = (i / frame.width() as usize) as f64 * self.font_size;
+            if glyph.bold != bold {
+                bold = glyph.bold;
+                let weight = if bold { "bold " } else { "" };
+                context.set_font(&format!("{}{}px monospace", weight, self.font_size));
+            }
+            context.set_global_alpha(if glyph.dim { DIM_ALPHA } else { 1.0 });
+            let color = css_color(glyph.color);
+            if glyph.reverse {
+                context.set_fill_style_str(&color);
+                context.fill_rect(x, y, self.cell_width, self.font_size);
+                context.set_fill_style_str(BACKGROUND);
+            } else {
+                context.set_fill_style_str(&color);
+            }
+            let mut buf = [0; 4];
+            context.fill_text(glyph.char.encode_utf8(&mut buf), x, y).map_err(js_error)?;
+        }
+        context.set_global_alpha(1.0);
+        Ok(())
+    }
+}
+
+/// The rain on a `<canvas>`, for JavaScript to step from `requestAnimationFrame`.
+#[wasm_bindgen]
+pub struct WebMatrix {
+    canvas: HtmlCanvasElement,
+    renderer: CanvasRenderer,
+    simulation: Simulation,
+}
+
+#[wasm_bindgen]
+impl WebMatrix {
+    /// Fills `canvas` with rain in characters `font_size` pixels tall.
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement, font_size: f64) -> Result<WebMatrix, JsValue> {
+        let renderer = CanvasRenderer::new(&canvas, font_size)?;
+        let (width, height) = renderer.grid_size(&canvas);
+        let mut simulation = Simulation::new(width, height, Config::new(&language_keys()));
+        simulation.rain_mut().truecolor = true;
+        simulation.rain_mut().dissolve = true;
+        Ok(Self { canvas, renderer, simulation })
+    }
+
+    /// Milliseconds between frames at the configured speed, for pacing `tick`.
+    #[wasm_bindgen(js_name = frameInterval)]
+    pub fn frame_interval(&self) -> f64 {
+        self.simulation.config().frame_interval().as_secs_f64() * 1000.0
+    }
+
+    /// Lays the rain out again after the canvas has changed size.
+    pub fn resize(&mut self) {
+        let (width, height) = self.renderer.grid_size(&self.canvas);
+        self.simulation.resize(width, height);
+    }
+
+    /// Advances the rain by one frame and draws it.
+    pub fn tick(&mut self) -> Result<(), JsValue> {
+        let frame = self.simulation.next_frame();
+        self.renderer.render(frame).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}